@@ -0,0 +1,49 @@
+//! Per-gate cost of Flatten, run with `cargo bench --features use_flatten`.
+//! Compares the in-place `flatten_matrix` to the previous row-wise
+//! implementation that round-tripped through `Vec<Vec<R>>`.
+
+use std::{hint::black_box, time::{Duration, Instant}};
+
+use gsw::{error_sampling::DiscrGaussianSampler, gsw::{gadget::Gadget, helper::{dmatrix_to_vec_of_vecs, flatten, flatten_matrix, vec_of_vecs_to_dmatrix}, FheScheme, GSW}, pow2_ring::Zpow2};
+use nalgebra::DMatrix;
+use num_traits::One;
+
+type R = Zpow2<31>;
+
+const ITERATIONS: u32 = 20;
+
+fn flatten_vec_of_vecs(a_matrix: &mut DMatrix<R>, gadget: &Gadget) {
+    let mut vec_of_vec = dmatrix_to_vec_of_vecs(a_matrix);
+    vec_of_vec.iter_mut().for_each(|row| flatten(row, gadget));
+    *a_matrix = vec_of_vecs_to_dmatrix(&vec_of_vec);
+}
+
+fn time(mut f: impl FnMut()) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    println!("{:>4} {:>6} {:>8} {:>14} {:>14} {:>14} {:>8}", "n", "base", "N", "nand", "flatten", "vec flatten", "speedup");
+    for n in [4, 8, 16] {
+        for gadget in [Gadget::BINARY, Gadget::new(4, true)] {
+            let fhe = GSW::<R, DiscrGaussianSampler>::new(n, n * 31, DiscrGaussianSampler::default(), gadget).unwrap();
+            let (_, pk) = fhe.keygen();
+            let one = fhe.encrypt(&pk, R::one());
+            let product = one.matrix().as_ref() * one.matrix().as_ref();
+
+            let nand = time(|| { black_box(fhe.nand(&one, &one).unwrap()); });
+            let in_place = time(|| flatten_matrix(&mut black_box(product.clone()), &gadget));
+            let round_trip = time(|| flatten_vec_of_vecs(&mut black_box(product.clone()), &gadget));
+
+            // nand includes one flatten, the old gate would have taken nand - in_place + round_trip
+            let speedup = (nand - in_place + round_trip).as_secs_f64() / nand.as_secs_f64();
+            println!("{:>4} {:>6} {:>8} {:>14?} {:>14?} {:>14?} {:>7.2}x",
+                n, gadget.base(), fhe.ciphertext_dim(), nand, in_place, round_trip, speedup);
+        }
+    }
+}
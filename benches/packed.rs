@@ -0,0 +1,43 @@
+//! Per-gate cost of multiplying by a bit-packed flattened ciphertext,
+//! run with `cargo bench --features use_flatten`.
+//! Compares `PackedBitMatrix::mul_dense` to the general dense product
+//! and reports the memory saved by storing ciphertexts packed.
+
+use std::{hint::black_box, time::{Duration, Instant}};
+
+use gsw::{error_sampling::DiscrGaussianSampler, gsw::{gadget::Gadget, FheScheme, GSW}, pow2_ring::Zpow2};
+use num_traits::One;
+
+type R = Zpow2<31>;
+
+const ITERATIONS: u32 = 20;
+
+fn time(mut f: impl FnMut()) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    println!("{:>4} {:>6} {:>14} {:>14} {:>14} {:>8} {:>8}", "n", "N", "nand", "masked", "dense", "speedup", "memory");
+    for n in [4, 8, 16] {
+        let fhe = GSW::<R, DiscrGaussianSampler>::new(n, n * 31, DiscrGaussianSampler::default(), Gadget::BINARY).unwrap();
+        let (_, pk) = fhe.keygen();
+        let one = fhe.encrypt(&pk, R::one());
+        let packed = one.packed().expect("Flattened binary ciphertexts are stored packed");
+        let dense_one = one.matrix().into_owned();
+
+        let nand = time(|| { black_box(fhe.nand(&one, &one).unwrap()); });
+        let masked = time(|| { black_box(packed.mul_dense(&dense_one)); });
+        let dense = time(|| { black_box(&dense_one * &dense_one); });
+
+        // nand includes one masked product, the dense gate would have taken nand - masked + dense
+        let speedup = (nand - masked + dense).as_secs_f64() / nand.as_secs_f64();
+        let memory = (dense_one.len() * size_of::<R>()) as f64 / one.size_in_bytes() as f64;
+        println!("{:>4} {:>6} {:>14?} {:>14?} {:>14?} {:>7.2}x {:>7.1}x",
+            n, fhe.ciphertext_dim(), nand, masked, dense, speedup, memory);
+    }
+}
@@ -0,0 +1,164 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::{error::TfheError, error_sampling::{rnd_dvec, rnd_seed, seeded_dmatrix, ErrorSampling}, gsw::{key_share::KeyShare, pk::{GswPk, Seed}, GSW}, secret::Secret, zo_sss::{Party, SecretSharingScheme}, RingElement, TfheStructure};
+
+
+/// Output of a single party in the dealer-free key generation.
+/// `b` is published, `deals[k]` is sent privately to the party named `deals[k].name`.
+pub struct DkgContribution<R: RingElement> {
+    pub name: u8,
+    pub b: DVector<R>,
+    pub deals: Vec<Party<R>>,
+}
+
+/// Dealer-free distributed key generation (no machine ever sees t).
+/// Every party i samples t_i and e_i and publishes b_i = B*t_i + e_i under a common B.
+/// The joint key is t = sum t_i with b = sum b_i = B*t + sum e_i.
+impl<R, S, T, F> TfheStructure<R, S, GSW<R, T>, F>
+where
+    R: RingElement + 'static,
+    S: SecretSharingScheme<R>,
+    T: ErrorSampling<R>,
+    F: ErrorSampling<R>,
+{
+    /// Common seed of the random matrix B, e.g. from a coin-flipping protocol.
+    /// Sampled locally here.
+    pub fn dkg_common_seed(&self) -> Seed {
+        rnd_seed()
+    }
+
+    /// Common random mxn matrix B all parties publish their b_i under, expanded from the common seed
+    pub fn dkg_common_matrix(&self, seed: &Seed) -> DMatrix<R> {
+        seeded_dmatrix(seed, self.fhe_scheme.m, self.fhe_scheme.n, 0, R::max_u64())
+    }
+
+    /// Run by party `name`.
+    /// Deals shares of s_i = (c, -t_i) where c = 1 for party 1 and c = 0 otherwise,
+    /// s.t. sum s_i = s = (1, -t). The constant is public so this does not leak anything.
+    /// t_i and e_i are wiped before returning, s_i is wiped by `SecretSharingScheme::share`.
+    pub fn dkg_contribute(&self, name: u8, random_matrix: &DMatrix<R>) -> DkgContribution<R> {
+        let t_i = Secret::new(rnd_dvec(self.fhe_scheme.n, 0, R::max_u64()));
+        let e_i = Secret::new(self.fhe_scheme.err_sampling.rnd_error_dvec(self.fhe_scheme.m));
+        let b = random_matrix * t_i.expose() + e_i.expose();
+
+        let constant = if name == 1 { R::one() } else { R::zero() };
+        // moved into share, which wipes it by contract
+        let s_i: Vec<R> = std::iter::once(constant)
+            .chain(t_i.expose().iter().map(|t| -*t))
+            .collect();
+        let deals = self.secret_sharing_scheme.share(s_i);
+
+        DkgContribution { name, b, deals }
+    }
+
+    /// Noise bound of fresh ciphertexts under the joint key of `num_parties` contributions.
+    /// The joint error is the sum of all e_i.
+    pub fn dkg_noise_bound(&self, num_parties: usize) -> u64 {
+        self.fhe_scheme.fresh_noise_bound().saturating_mul(num_parties as u64)
+    }
+
+    /// Bound on |e|_inf of the joint key, see `dkg_noise_bound`
+    fn dkg_error_bound(&self, num_parties: usize) -> u64 {
+        self.fhe_scheme.err_sampling.error_bound().saturating_mul(num_parties as u64)
+    }
+
+    /// Aggregates the published b_i into the joint public key, which can be shipped as (seed, b).
+    /// Fails if the joint error can exceed the decoding window of `decrypt`.
+    pub fn dkg_public_key(&self, seed: &Seed, contributions: &[&DVector<R>]) -> Result<GswPk<R>, TfheError> {
+        let bound = self.dkg_noise_bound(contributions.len());
+        let window = self.fhe_scheme.noise_window();
+        if bound >= window {
+            return Err(TfheError::NoiseTooLarge { bound, window });
+        }
+
+        let b = contributions.iter()
+            .fold(DVector::zeros(self.fhe_scheme.m), |acc, b_i| acc + *b_i);
+        Ok(GswPk::from_seed_parts(*seed, b, self.fhe_scheme.n).with_error_bound(self.dkg_error_bound(contributions.len())))
+    }
+
+    /// Run by every party on the deals it received (one per contributing party).
+    /// Summing the shares of all s_i yields shares of s which are expanded to shares of v.
+    pub fn dkg_key_share(&self, deals: &[Party<R>]) -> KeyShare<R> {
+        let (first, rest) = deals.split_first().expect("Need at least one deal");
+        let s_shares = rest.iter().fold(first.clone(), |mut acc, deal| {
+            acc.add_shares(deal);
+            acc
+        });
+        KeyShare::from_s_shares(s_shares, self.fhe_scheme.gadget())
+    }
+
+    /// Runs all steps of the DKG for every party locally
+    pub fn dkg_setup(&self) -> Result<(Vec<KeyShare<R>>, GswPk<R>), TfheError> {
+        let seed = self.dkg_common_seed();
+        let random_matrix = self.dkg_common_matrix(&seed);
+        let num_parties = self.secret_sharing_scheme.num_parties();
+        let contributions: Vec<DkgContribution<R>> = (1..=num_parties as u8)
+            .map(|name| self.dkg_contribute(name, &random_matrix))
+            .collect();
+
+        let published: Vec<&DVector<R>> = contributions.iter().map(|c| &c.b).collect();
+        let pk = self.dkg_public_key(&seed, &published)?;
+
+        let key_shares = (1..=num_parties as u8)
+            .map(|name| {
+                let received: Vec<Party<R>> = contributions.iter()
+                    .flat_map(|c| c.deals.iter().filter(|d| d.name == name).cloned())
+                    .collect();
+                self.dkg_key_share(&received)
+            })
+            .collect();
+        Ok((key_shares, pk))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DVector;
+    use num_traits::{One, Zero};
+
+    use crate::{error::TfheError, error_sampling::{DiscrGaussianSampler, NaiveSampler, UniformSampler}, gsw::{gadget::Gadget, key_share::KeyShare, params::SecurityLevel, sk::GswSk, GSW}, pow2_ring::Zpow2, zo_sss::{dimacs::{DIMACS, DIMACS_2_OF_3_SCHEME, DIMACS_AB_OR_CD}, MBF}, PartialDecryption, RingElement, TfheScheme, TfheStructure};
+
+    #[test]
+    fn dkg_threshold_decryption() {
+        let gsw = GSW::<Zpow2<31>, DiscrGaussianSampler>::preset(SecurityLevel::Toy).unwrap();
+        let tfhe = TfheStructure::new(MBF::new(DIMACS::parse(DIMACS_AB_OR_CD)), gsw, UniformSampler::for_shares(1 << 26, 4));
+        let (key_shares, pk) = tfhe.dkg_setup().unwrap();
+        assert_eq!(key_shares.len(), 4);
+        assert!(tfhe.fhe_scheme.decompress_pk(&pk.compress().unwrap()).is_ok_and(|decompressed| decompressed == pk));
+        assert_eq!(tfhe.check_smudging(tfhe.dkg_noise_bound(4)), Ok(()));
+        assert_eq!(tfhe.fhe_scheme.pk_noise_bound(&pk), tfhe.dkg_noise_bound(4));
+
+        // shares of the joint s = (1, -t) expand to shares of v = powers_of_2(s)
+        let v = KeyShare::combine(&key_shares, &tfhe.secret_sharing_scheme);
+        let v = v.expose();
+        let t = DVector::from_iterator(4, (1..=4).map(|k| -v[k * Zpow2::<31>::Num_Bits]));
+        let sk = GswSk::new(t, &Gadget::BINARY);
+        assert_eq!(sk.v(), v);
+
+        for (msg, expected) in [(Zpow2::zero(), Zpow2::zero()), (Zpow2::one(), Zpow2::one())] {
+            let ct = tfhe.encrypt(&pk, msg);
+            assert_eq!(ct.noise_bound(), tfhe.dkg_noise_bound(4));
+            let part_decs: Vec<PartialDecryption<Zpow2<31>>> = key_shares.iter()
+                .map(|ks| tfhe.part_dec(&pk, &ct, ks).unwrap())
+                .collect();
+            assert_eq!(tfhe.fin_dec(&part_decs[..2]), Ok(expected));
+            assert_eq!(tfhe.fin_dec(&part_decs[2..]), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn dkg_rejects_large_joint_error() {
+        let gsw = GSW::<Zpow2<31>, NaiveSampler>::new(4, 4 * Zpow2::<31>::Num_Bits, NaiveSampler, Gadget::BINARY).unwrap();
+        let tfhe = TfheStructure::new(MBF::new(DIMACS::parse(DIMACS_2_OF_3_SCHEME)), gsw, UniformSampler::new(0));
+        let seed = tfhe.dkg_common_seed();
+        let random_matrix = tfhe.dkg_common_matrix(&seed);
+        let contributions: Vec<_> = (1..=3).map(|name| tfhe.dkg_contribute(name, &random_matrix)).collect();
+        let published: Vec<_> = contributions.iter().map(|c| &c.b).collect();
+        assert!(tfhe.dkg_public_key(&seed, &published).is_ok());
+
+        // 124 * 65535 * 100 > 2^31 / 4
+        let many: Vec<_> = published.iter().cycle().take(100).copied().collect();
+        assert!(matches!(tfhe.dkg_public_key(&seed, &many), Err(TfheError::NoiseTooLarge { .. })));
+    }
+}
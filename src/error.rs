@@ -0,0 +1,38 @@
+use std::fmt;
+
+
+/// Errors surfaced by the threshold layer and parameter validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TfheError {
+    /// The contributing parties (by name) do not satisfy the access structure
+    Unqualified(Vec<u8>),
+    /// Ciphertext noise plus flooding noise of all summed shares exceeds the decoding window
+    SmudgingTooLarge { total: u64, window: u64 },
+    /// The (aggregated) key error can exceed the decoding window
+    NoiseTooLarge { bound: u64, window: u64 },
+    /// Qualified sets decode to different values and no unique set of faulty parties explains it.
+    /// Contains the parties that might be faulty.
+    Inconsistent(Vec<u8>),
+    /// Scheme parameters violate a constraint (see `GSW::new`)
+    InvalidParameters(String),
+    /// Ciphertexts were created under different keys or parameter sets
+    IncompatibleCiphertexts,
+    /// The message bound exceeds the range in which Z_t arithmetic is exact
+    MessageOverflow { bound: u64, window: u64 },
+}
+
+impl fmt::Display for TfheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TfheError::Unqualified(names) => write!(f, "Parties {:?} do not satisfy the access structure", names),
+            TfheError::SmudgingTooLarge { total, window } => write!(f, "Total noise bound {} exceeds the decoding window {}", total, window),
+            TfheError::NoiseTooLarge { bound, window } => write!(f, "Noise bound {} exceeds the decoding window {}", bound, window),
+            TfheError::Inconsistent(names) => write!(f, "Partial decryptions are inconsistent, one of {:?} is faulty", names),
+            TfheError::InvalidParameters(reason) => write!(f, "Invalid parameters: {}", reason),
+            TfheError::IncompatibleCiphertexts => write!(f, "Ciphertexts belong to different keys or parameter sets"),
+            TfheError::MessageOverflow { bound, window } => write!(f, "Message bound {} exceeds the message window {}", bound, window),
+        }
+    }
+}
+
+impl std::error::Error for TfheError {}
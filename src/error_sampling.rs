@@ -2,50 +2,86 @@ use crate::{field::P, RingElement};
 
 use nalgebra::{DVector, DMatrix};
 use num_bigint::{BigUint, Sign};
+use num_traits::ToPrimitive;
 use num_rational::Ratio;
 use prio::dp::distributions::DiscreteGaussian;
-use rand::{distr::Distribution, rng, Rng};
+use rand::{distr::Distribution, rng, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 
 const NOISE_CONST: u64 = 1u64;
+// Gaussian samples exceed TAIL_CUT * stddev with probability < 2^-100
+const TAIL_CUT: u64 = 12u64;
 
 
 pub fn rnd_dmatrix<R: RingElement + 'static>(nrows: usize, ncols: usize, min: u64, max: u64) -> DMatrix<R> {
     DMatrix::from_fn(nrows, ncols, |_, _| rnd_ring_elm(min, max))
 }
 
+/// Deterministic counterpart of `rnd_dmatrix`: the entries are drawn in column major order
+/// from ChaCha20 seeded with `seed`, so the matrix can be shipped as its seed.
+/// Every entry is min + x for the first output x of `next_u64`, masked to the bit length of
+/// max - min, that is at most max - min (rejection sampling). Unlike `random_range`
+/// this only depends on the ChaCha20 stream, which does not change between `rand` versions.
+pub fn seeded_dmatrix<R: RingElement + 'static>(seed: &[u8; 32], nrows: usize, ncols: usize, min: u64, max: u64) -> DMatrix<R> {
+    assert!(min <= max && max <= P);
+    let mut rng = ChaCha20Rng::from_seed(*seed);
+    let span = max - min;
+    let mask = u64::MAX.checked_shr(span.leading_zeros()).unwrap_or(0);
+    DMatrix::from_fn(nrows, ncols, |_, _| loop {
+        let x = rng.next_u64() & mask;
+        if x <= span {
+            break R::from(min + x);
+        }
+    })
+}
+
+pub fn rnd_seed() -> [u8; 32] {
+    rand::rng().random()
+}
+
 pub fn rnd_dvec<R: RingElement + 'static>(size: usize, min: u64, max: u64) -> DVector<R> {
     DVector::from_fn(size,  |_,_| rnd_ring_elm(min, max))
 }
 
+/// Uniform in [min, max] for max <= P, so rings wider than 31 bits cannot be sampled uniformly
 pub fn rnd_ring_elm<R: RingElement>(min: u64, max: u64) -> R {
     assert!(max <= P);
     let mut rng = rand::rng();
     R::from(rng.random_range(min..=max))
 }
 
+/// Uniform over the whole ring, also for rings wider than 31 bits (unlike `rnd_ring_elm`)
+pub fn rnd_uniform_ring_elm<R: RingElement>() -> R {
+    let mut rng = rand::rng();
+    R::from(rng.random_range(0..=R::max_u64()))
+}
+
 //Do not use for sampling random numbers as domain of error functions is restricted!
 pub trait ErrorSampling<R: RingElement> {
     fn rnd_error_elm(&self) -> R;
     fn rnd_error_dvec(&self, size: usize) -> DVector<R>;
+    /// (High probability) bound on the absolute value of a single sample
+    fn error_bound(&self) -> u64;
 }
 
 pub struct DiscrGaussianSampler {
     sampler: DiscreteGaussian,
+    bound: u64,
 }
 
 impl DiscrGaussianSampler {
     pub fn new(stddev: Ratio<BigUint>) -> Self {
+        let bound = stddev.ceil().to_integer().to_u64().expect("stddev must fit into u64") * TAIL_CUT * NOISE_CONST;
         let sampler = DiscreteGaussian::new(stddev).expect("Failed to create DiscreteGaussian");
         Self {
             sampler,
+            bound,
         }
     }
 
     //completely made up number
     pub fn default() -> Self {
-        Self {
-            sampler: DiscreteGaussian::new(Ratio::<BigUint>::new(BigUint::from(1u32), BigUint::from(1u32))).unwrap()
-        }
+        Self::new(Ratio::<BigUint>::new(BigUint::from(1u32), BigUint::from(1u32)))
     }
 }
 
@@ -68,6 +104,10 @@ impl<R: RingElement+ 'static> ErrorSampling<R> for DiscrGaussianSampler {
         DVector::from_iterator(size, (0..size).map(|_| Self::rnd_error_elm(self)))
     }
 
+    fn error_bound(&self) -> u64 {
+        self.bound
+    }
+
 }
 
 pub struct NaiveSampler;
@@ -81,14 +121,52 @@ impl<R: RingElement + 'static> ErrorSampling<R> for NaiveSampler {
     fn rnd_error_dvec(&self, size: usize) -> DVector<R> {
         DVector::from_fn(size,  |_,_| self.rnd_error_elm())
     }
+
+    fn error_bound(&self) -> u64 {
+        (P>>15) * NOISE_CONST
+    }
+}
+
+/// Uniform noise in [-bound, bound].
+/// Used for noise flooding (smudging) of partial decryptions.
+pub struct UniformSampler {
+    bound: u64,
+}
+
+impl UniformSampler {
+    pub fn new(bound: u64) -> Self {
+        assert!(2 * bound <= P, "bound must be at most P/2");
+        Self { bound }
+    }
+
+    /// Splits a total noise budget between `num_shares` shares,
+    /// s.t. the sum of all flooded shares stays below `budget`
+    pub fn for_shares(budget: u64, num_shares: usize) -> Self {
+        assert!(num_shares > 0, "Need at least one share");
+        Self::new(budget / num_shares as u64)
+    }
+}
+
+impl<R: RingElement + 'static> ErrorSampling<R> for UniformSampler {
+    fn rnd_error_elm(&self) -> R {
+        rnd_ring_elm::<R>(0, 2 * self.bound) - R::from(self.bound)
+    }
+
+    fn rnd_error_dvec(&self, size: usize) -> DVector<R> {
+        DVector::from_fn(size,  |_,_| self.rnd_error_elm())
+    }
+
+    fn error_bound(&self) -> u64 {
+        self.bound
+    }
 }
 
 #[cfg(test)]
 mod test {
 
-    use nalgebra::DVector;
+    use nalgebra::{DMatrix, DVector};
 
-    use crate::{error_sampling::{DiscrGaussianSampler, ErrorSampling}, field::Fp};
+    use crate::{error_sampling::{seeded_dmatrix, DiscrGaussianSampler, ErrorSampling, UniformSampler}, field::Fp, pow2_ring::Zpow2, RingElement};
 
     #[test]
     fn gaussian_test() {
@@ -102,4 +180,26 @@ mod test {
         let all_same = rnd_vec.iter().all(|x| x == first);
         assert!(!all_same, "All sampled values are identical — Gaussian sampler may be broken");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn uniform_test() {
+        let uniform = UniformSampler::for_shares(300, 3);
+        assert_eq!(ErrorSampling::<Fp>::error_bound(&uniform), 100);
+
+        let rnd_vec: DVector<Fp> = uniform.rnd_error_dvec(1000);
+        for x in rnd_vec.iter() {
+            let abs = std::cmp::min(Into::<u64>::into(*x), Into::<u64>::into(-*x));
+            assert!(abs <= 100, "{abs} exceeds the bound");
+        }
+    }
+
+    #[test]
+    fn seeded_known_answer() {
+        // pins the expansion of seeds, compressed public keys depend on it
+        let values = |m: DMatrix<Zpow2<31>>| m.iter().map(|x| Into::<u64>::into(*x)).collect::<Vec<_>>();
+        let matrix: DMatrix<Fp> = seeded_dmatrix(&[42; 32], 2, 2, 0, Fp::max_u64());
+        assert_eq!(matrix.iter().map(|x| Into::<u64>::into(*x)).collect::<Vec<_>>(), vec![1176443288, 389436484, 464927326, 2078710526]);
+        assert_eq!(values(seeded_dmatrix(&[42; 32], 1, 4, 3, 5)), vec![3, 3, 5, 5]);
+        assert_eq!(values(seeded_dmatrix(&[42; 32], 1, 2, 7, 7)), vec![7, 7]);
+    }
+}
@@ -39,6 +39,9 @@ impl RingElement for Fp {
     const Num_Bits: usize = Fp::NUM_BITS as usize;
 }
 
+/// Default is zero, so secrets in Fp can be wiped
+impl zeroize::DefaultIsZeroes for Fp {}
+
 impl Bounded for Fp {
     fn min_value() -> Self {
         Self::ZERO
@@ -0,0 +1,149 @@
+use std::borrow::Cow;
+
+use nalgebra::DMatrix;
+
+use crate::{error::TfheError, gsw::{helper::inf_norm, packed::PackedBitMatrix}, RingElement};
+
+
+/// Identifies the parameter set and key a ciphertext was created under.
+/// `key` is the fingerprint of the public key (see `GswPk::fingerprint`),
+/// `None` for ciphertexts that are valid under every key (trivial encryptions).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    pub params: u64,
+    pub key: Option<u64>,
+}
+
+impl Fingerprint {
+    /// Fingerprint of the result of an operation on ciphertexts with fingerprints self and other.
+    /// Fails if they belong to different parameter sets or keys.
+    pub fn combine(&self, other: &Fingerprint) -> Result<Fingerprint, TfheError> {
+        let key = match (self.key, other.key) {
+            (Some(a), Some(b)) if a != b => None,
+            (a, b) => Some(a.or(b)),
+        };
+        match key {
+            Some(key) if self.params == other.params => Ok(Fingerprint { params: self.params, key }),
+            _ => Err(TfheError::IncompatibleCiphertexts),
+        }
+    }
+}
+
+/// 64-bit FNV-1a of the bytes. Unlike `DefaultHasher` the result is fixed
+/// across Rust releases and platforms, so fingerprints stay comparable between builds.
+pub(crate) fn stable_hash(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    bytes.into_iter().fold(OFFSET_BASIS, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
+
+/// Entries of a ciphertext. Flattened ciphertexts under `Gadget::BINARY` only have bits
+/// as entries, they are packed once when they are flattened and stay packed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CiphertextMatrix<R: RingElement> {
+    Dense(DMatrix<R>),
+    Packed(PackedBitMatrix),
+}
+
+/// GSW ciphertext C with C*v = mu*v + e.
+/// Carries analytic bounds on |e|_inf and |mu| (see `GSW::fresh_message_bound` for fresh encryptions),
+/// which are updated by every homomorphic operation.
+/// `flattened` records whether Flatten was applied, i.e. all entries are gadget digits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GswCiphertext<R: RingElement> {
+    matrix: CiphertextMatrix<R>,
+    fingerprint: Fingerprint,
+    flattened: bool,
+    noise_bound: u64,
+    message_bound: u64,
+}
+
+impl<R: RingElement + 'static> GswCiphertext<R> {
+    pub(crate) fn new(matrix: CiphertextMatrix<R>, fingerprint: Fingerprint, flattened: bool, noise_bound: u64, message_bound: u64) -> Self {
+        Self { matrix, fingerprint, flattened, noise_bound, message_bound }
+    }
+
+    /// Entries of C, unpacked if the ciphertext is stored packed
+    pub fn matrix(&self) -> Cow<'_, DMatrix<R>> {
+        match &self.matrix {
+            CiphertextMatrix::Dense(matrix) => Cow::Borrowed(matrix),
+            CiphertextMatrix::Packed(packed) => Cow::Owned(packed.unpack()),
+        }
+    }
+
+    /// Bit-packed entries, `None` if the ciphertext is stored dense
+    pub fn packed(&self) -> Option<&PackedBitMatrix> {
+        match &self.matrix {
+            CiphertextMatrix::Dense(_) => None,
+            CiphertextMatrix::Packed(packed) => Some(packed),
+        }
+    }
+
+    /// `count` consecutive rows of C starting at `start`, without unpacking the others
+    pub fn rows(&self, start: usize, count: usize) -> DMatrix<R> {
+        match &self.matrix {
+            CiphertextMatrix::Dense(matrix) => matrix.rows(start, count).into_owned(),
+            CiphertextMatrix::Packed(packed) => packed.unpack_rows(start, count),
+        }
+    }
+
+    /// Infinity norm of C (see `helper::inf_norm`), the number of ones per row if packed
+    pub fn inf_norm(&self) -> u64 {
+        match &self.matrix {
+            CiphertextMatrix::Dense(matrix) => inf_norm(matrix),
+            CiphertextMatrix::Packed(packed) => packed.max_row_weight(),
+        }
+    }
+
+    /// Number of bytes used for the entries
+    pub fn size_in_bytes(&self) -> usize {
+        match &self.matrix {
+            CiphertextMatrix::Dense(matrix) => matrix.len() * size_of::<R>(),
+            CiphertextMatrix::Packed(packed) => packed.size_in_bytes(),
+        }
+    }
+
+    pub fn fingerprint(&self) -> &Fingerprint {
+        &self.fingerprint
+    }
+
+    pub fn is_flattened(&self) -> bool {
+        self.flattened
+    }
+
+    pub fn noise_bound(&self) -> u64 {
+        self.noise_bound
+    }
+
+    pub fn message_bound(&self) -> u64 {
+        self.message_bound
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::TfheError, gsw::ciphertext::{stable_hash, Fingerprint}};
+
+    #[test]
+    fn stable_hash_vectors() {
+        assert_eq!(stable_hash([]), 0xcbf29ce484222325);
+        assert_eq!(stable_hash(*b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(stable_hash(*b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn combine_fingerprints() {
+        let key1 = Fingerprint { params: 1, key: Some(1) };
+        let key2 = Fingerprint { params: 1, key: Some(2) };
+        let trivial = Fingerprint { params: 1, key: None };
+        let other_params = Fingerprint { params: 2, key: None };
+
+        assert_eq!(key1.combine(&key1), Ok(key1));
+        assert_eq!(key1.combine(&trivial), Ok(key1));
+        assert_eq!(trivial.combine(&key2), Ok(key2));
+        assert_eq!(trivial.combine(&trivial), Ok(trivial));
+        assert_eq!(key1.combine(&key2), Err(TfheError::IncompatibleCiphertexts));
+        assert_eq!(trivial.combine(&other_params), Err(TfheError::IncompatibleCiphertexts));
+    }
+}
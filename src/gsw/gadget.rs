@@ -0,0 +1,149 @@
+use nalgebra::DVector;
+
+use crate::RingElement;
+
+
+/// Gadget g = (1, B, B^2, ..., B^{l-1}) with base B = 2^k and l = ceil(Num_Bits / k).
+/// Decomposition produces l digits per ring element, either in [0, B)
+/// or balanced (in [-B/2, B/2), the most significant digit may exceed this by one).
+/// A larger base shrinks ciphertexts (l*(n+1) rows) at the cost of more noise per operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Gadget {
+    log_base: usize,
+    balanced: bool,
+}
+
+impl Gadget {
+    /// Base 2 with bits as digits (BitDecomp / PowersOf2 of GSW)
+    pub const BINARY: Gadget = Gadget { log_base: 1, balanced: false };
+
+    pub fn new(log_base: usize, balanced: bool) -> Self {
+        assert!((1..64).contains(&log_base), "log_base must be in 1..64");
+        Self { log_base, balanced }
+    }
+
+    pub fn log_base(&self) -> usize {
+        self.log_base
+    }
+
+    pub fn base(&self) -> u64 {
+        1u64 << self.log_base
+    }
+
+    pub fn is_balanced(&self) -> bool {
+        self.balanced
+    }
+
+    /// Number of digits l per ring element
+    pub fn num_digits<R: RingElement>(&self) -> usize {
+        R::Num_Bits.div_ceil(self.log_base)
+    }
+
+    /// Bound on the absolute value of a digit
+    pub fn digit_bound(&self) -> u64 {
+        if self.balanced { self.base() / 2 + 1 } else { self.base() - 1 }
+    }
+
+    /// (1, B, ..., B^{l-1})
+    pub fn vector<R: RingElement + 'static>(&self) -> DVector<R> {
+        DVector::from_iterator(self.num_digits::<R>(), (0..self.num_digits::<R>()).map(|i| self.power(i)))
+    }
+
+    /// B^i
+    pub fn power<R: RingElement>(&self, i: usize) -> R {
+        R::from(1u64 << (i * self.log_base))
+    }
+
+    /// Appends the little endian digits d_i of x to `out` s.t. sum d_i B^i = x
+    pub fn decompose<R: RingElement>(&self, x: R, out: &mut Vec<R>) {
+        let start = out.len();
+        out.resize(start + self.num_digits::<R>(), R::zero());
+        self.decompose_into(x, &mut out[start..]);
+    }
+
+    /// Writes the l little endian digits of x into `out` (of length l)
+    pub fn decompose_into<R: RingElement>(&self, x: R, out: &mut [R]) {
+        debug_assert_eq!(out.len(), self.num_digits::<R>());
+        if self.balanced {
+            // decompose the centered representative so that digits can be negative
+            let value: u64 = x.into();
+            let modulus = R::max_u64() as i128 + 1;
+            let mut rest = if value > R::max_u64() / 2 { value as i128 - modulus } else { value as i128 };
+            let base = self.base() as i128;
+
+            let (last, lower) = out.split_last_mut().expect("Gadget has at least one digit");
+            for digit_out in lower.iter_mut() {
+                let mut digit = rest.rem_euclid(base);
+                if digit >= base / 2 {
+                    digit -= base;
+                }
+                rest = (rest - digit) / base;
+                *digit_out = signed_to_ring(digit);
+            }
+            *last = signed_to_ring(rest);
+        } else {
+            let mut rest: u64 = x.into();
+            let mask = self.base() - 1;
+            for digit_out in out.iter_mut() {
+                *digit_out = R::from(rest & mask);
+                rest >>= self.log_base;
+            }
+        }
+    }
+
+    /// sum d_i B^i for arbitrary (not necessarily small) d_i
+    pub fn compose<R: RingElement>(&self, digits: &[R]) -> R {
+        digits.iter()
+            .enumerate()
+            .fold(R::zero(), |acc, (i, d)| acc + *d * self.power(i))
+    }
+}
+
+impl Default for Gadget {
+    fn default() -> Self {
+        Self::BINARY
+    }
+}
+
+fn signed_to_ring<R: RingElement>(x: i128) -> R {
+    if x >= 0 { R::from(x as u64) } else { -R::from((-x) as u64) }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use crate::{field::Fp, gsw::{gadget::Gadget, helper::centered_abs}, pow2_ring::Zpow2, RingElement};
+
+    fn decompose_compose<R: RingElement + 'static>(gadget: Gadget) {
+        let mut rng = rand::rng();
+        for _ in 0..100 {
+            let x = R::from(rng.random_range(0..=R::max_u64()));
+            let mut digits = vec![];
+            gadget.decompose(x, &mut digits);
+            assert_eq!(digits.len(), gadget.num_digits::<R>());
+            assert!(digits.iter().all(|d| centered_abs(*d) <= gadget.digit_bound()), "{:?}", digits);
+            assert_eq!(gadget.compose(&digits), x);
+        }
+    }
+
+    #[test]
+    fn digits_recompose() {
+        for log_base in [1, 2, 3, 4, 8, 16] {
+            for balanced in [false, true] {
+                decompose_compose::<Fp>(Gadget::new(log_base, balanced));
+                decompose_compose::<Zpow2<31>>(Gadget::new(log_base, balanced));
+                decompose_compose::<Zpow2<64>>(Gadget::new(log_base, balanced));
+            }
+        }
+    }
+
+    #[test]
+    fn gadget_length() {
+        assert_eq!(Gadget::BINARY.num_digits::<Fp>(), Fp::Num_Bits);
+        assert_eq!(Gadget::new(4, false).num_digits::<Fp>(), 8);
+        assert_eq!(Gadget::new(4, false).num_digits::<Zpow2<32>>(), 8);
+        assert_eq!(Gadget::new(4, false).vector::<Zpow2<32>>()[2], Zpow2::from(256));
+    }
+}
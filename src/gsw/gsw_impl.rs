@@ -1,145 +1,530 @@
-use std::any::TypeId;
-
 use nalgebra::{DMatrix, DVector};
 
 use crate::{
-    error_sampling::{rnd_dmatrix, rnd_dvec, ErrorSampling}, field::Fp, gsw::{helper::bit_decomp_matrix, pk::GswPk, sk::GswSk, FheScheme, GSW}, RingElement
+    error::TfheError, error_sampling::{rnd_dmatrix, rnd_dvec, rnd_seed, ErrorSampling}, gsw::{ciphertext::{stable_hash, CiphertextMatrix, Fingerprint, GswCiphertext}, gadget::Gadget, helper::{bit_decomp_matrix, centered_abs, flatten_matrix}, matmul::mat_mul, packed::PackedBitMatrix, pk::GswPk, sk::GswSk, FheScheme, GSW}, secret::Secret, RingElement
 };
 
-#[cfg(feature="use_flatten")]
-use crate::{gsw::helper::flatten_matrix};
+impl<R: RingElement + 'static, T: ErrorSampling<R>> GSW<R, T> {
+    /// Number of rows (and columns) of a ciphertext: N = l * (n + 1)
+    pub fn ciphertext_dim(&self) -> usize {
+        self.gadget.num_digits::<R>() * (self.n + 1)
+    }
+
+    pub fn gadget(&self) -> &Gadget {
+        &self.gadget
+    }
+
+    /// Row of the ciphertext used in decryption.
+    /// Its scalar product with v equals B^{l-1} * mu + noise
+    pub fn decryption_row(&self, ciphertext: &GswCiphertext<R>) -> DVector<R> {
+        ciphertext.rows(self.gadget.num_digits::<R>() - 1, 1).row(0).transpose()
+    }
+
+    /// Decryption rounds to the closest of 0 and B^{l-1}*mu, which is correct as long as |e| < B^{l-1}/2
+    pub fn noise_window(&self) -> u64 {
+        1u64 << (self.gadget.log_base() * (self.gadget.num_digits::<R>() - 1) - 1)
+    }
+
+    /// For pow2 rings `mp_decrypt` recovers up to k bits per gadget row by rounding to multiples of 2^{Num_Bits-k},
+    /// which is correct as long as |e| < 2^{Num_Bits-k-1}.
+    /// For odd moduli q it needs (B+1)*|e| < q/2 and |e| < B^{l-1}/2 (see `recover_by_lifting`).
+    pub fn mp_noise_window(&self) -> u64 {
+        if is_pow2_modulus::<R>() {
+            let new_bits = std::cmp::min(self.gadget.log_base(), R::Num_Bits - 1);
+            return 1u64 << (R::Num_Bits - new_bits - 1);
+        }
+        let q = R::max_u64() as u128 + 1;
+        let lifting = q / (2 * (self.gadget.base() as u128 + 1));
+        std::cmp::min(lifting as u64, self.noise_window())
+    }
+
+    /// Noise that can still be added before decryption fails
+    pub fn noise_budget(&self, ciphertext: &GswCiphertext<R>) -> u64 {
+        self.noise_window().saturating_sub(ciphertext.noise_bound())
+    }
+
+    /// Applies Flatten to the result of an operation if the `use_flatten` feature is enabled
+    fn flatten_if_enabled(&self, mut matrix: DMatrix<R>) -> CiphertextMatrix<R> {
+        if !cfg!(feature="use_flatten") {
+            return CiphertextMatrix::Dense(matrix);
+        }
+        flatten_matrix(&mut matrix, &self.gadget);
+        self.store_flattened(matrix)
+    }
+
+    /// All entries of a flattened matrix are digits, under the binary gadget they are bits.
+    /// Such matrices are packed here, once per ciphertext, and stay packed.
+    pub(crate) fn store_flattened(&self, matrix: DMatrix<R>) -> CiphertextMatrix<R> {
+        if self.gadget == Gadget::BINARY && let Some(packed) = PackedBitMatrix::pack(&matrix) {
+            return CiphertextMatrix::Packed(packed);
+        }
+        CiphertextMatrix::Dense(matrix)
+    }
+
+    /// C1*C2. If C1 is stored packed the product is computed by masked accumulation.
+    fn product(&self, ciphertext1: &GswCiphertext<R>, ciphertext2: &GswCiphertext<R>) -> DMatrix<R> {
+        match ciphertext1.packed() {
+            Some(packed) => packed.mul_dense(&ciphertext2.matrix()),
+            None => mat_mul(&ciphertext1.matrix(), &ciphertext2.matrix()),
+        }
+    }
+
+    /// Fails if the noise bound of the ciphertext reaches `window`
+    fn check_noise(ciphertext: &GswCiphertext<R>, window: u64) -> Result<(), TfheError> {
+        if ciphertext.noise_bound() < window {
+            Ok(())
+        } else {
+            Err(TfheError::NoiseTooLarge { bound: ciphertext.noise_bound(), window })
+        }
+    }
+
+    /// Hash of the ring (modulus and bit size) and all parameters that determine the ciphertext layout
+    pub fn params_fingerprint(&self) -> u64 {
+        let params = [
+            R::max_u64(), R::Num_Bits as u64, self.n as u64, self.m as u64,
+            self.gadget.log_base() as u64, self.gadget.is_balanced() as u64, self.message_modulus,
+        ];
+        stable_hash(params.into_iter().flat_map(u64::to_le_bytes))
+    }
+
+    /// Fails if the ciphertext was created under different parameters than this scheme
+    fn check_params(&self, ciphertext: &GswCiphertext<R>) -> Result<(), TfheError> {
+        if ciphertext.fingerprint().params != self.params_fingerprint() {
+            return Err(TfheError::IncompatibleCiphertexts);
+        }
+        Ok(())
+    }
+
+    /// Fingerprint of the ciphertext bound to pk.
+    /// Fails if it was created under different parameters or another key (trivial encryptions match every key).
+    pub fn check_pk(&self, pk: &GswPk<R>, ciphertext: &GswCiphertext<R>) -> Result<Fingerprint, TfheError> {
+        let pk_fingerprint = Fingerprint { params: self.params_fingerprint(), key: Some(pk.fingerprint()) };
+        ciphertext.fingerprint().combine(&pk_fingerprint)
+    }
+
+    /// Fingerprint of the result of an operation on both ciphertexts.
+    /// Fails if they were created under different keys or parameters than this scheme.
+    fn combine_fingerprints(&self, ciphertext1: &GswCiphertext<R>, ciphertext2: &GswCiphertext<R>) -> Result<Fingerprint, TfheError> {
+        let fingerprint = ciphertext1.fingerprint().combine(ciphertext2.fingerprint())?;
+        if fingerprint.params != self.params_fingerprint() {
+            return Err(TfheError::IncompatibleCiphertexts);
+        }
+        Ok(fingerprint)
+    }
+
+    /// Noiseless encryption mu*I of a public message.
+    /// Deterministic, so parties evaluating the same circuit obtain the same ciphertext.
+    /// Not bound to a key, so it can be combined with ciphertexts under any key.
+    pub fn trivial_encrypt(&self, message: R) -> GswCiphertext<R> {
+        let big_n = self.ciphertext_dim();
+        let fingerprint = Fingerprint { params: self.params_fingerprint(), key: None };
+        GswCiphertext::new(CiphertextMatrix::Dense(DMatrix::from_diagonal_element(big_n, big_n, message)), fingerprint, false, 0, centered_abs(message))
+    }
+
+    /// First l rows of the ciphertext. Their product with v equals
+    /// (mu, B*mu, ..., B^{l-1} * mu) + noise
+    pub fn mp_decryption_rows(&self, ciphertext: &GswCiphertext<R>) -> DMatrix<R> {
+        ciphertext.rows(0, self.gadget.num_digits::<R>())
+    }
+
+    /// Bound on the noise in <decryption_row, v> of a fresh ciphertext under a key from `keygen`:
+    /// the row equals BitDecomp(R*A) + mu*I where R is binary, so
+    /// <row, v> = B^{l-1} * mu + <r, e> and |<r, e>| <= m * |e|_inf
+    pub fn fresh_noise_bound(&self) -> u64 {
+        self.m as u64 * self.err_sampling.error_bound()
+    }
+
+    /// Same as `fresh_noise_bound` for the error bound of the given key (e.g. a DKG key)
+    pub fn pk_noise_bound(&self, pk: &GswPk<R>) -> u64 {
+        (self.m as u64).saturating_mul(pk.error_bound())
+    }
+
+    /// Bound on the noise in <decryption_row, v> of a fresh secret key encryption
+    pub fn fresh_sk_noise_bound(&self) -> u64 {
+        self.err_sampling.error_bound()
+    }
+
+    /// Message bound of a fresh encryption of `message`.
+    /// Every message in [0, t) gets the same bound t-1, so the bound does not reveal the message.
+    /// `encrypt` takes any ring element (`mp_decrypt` recovers it unchanged) and does not reduce it,
+    /// larger messages are tracked with their actual size. `encrypt_message` rejects them instead.
+    fn fresh_message_bound(&self, message: R) -> u64 {
+        std::cmp::max(self.message_modulus - 1, centered_abs(message))
+    }
+
+    /// Product of all ciphertexts as the left leaning chain acc <- Flatten(acc*C_i) over the inputs in order.
+    /// In acc*C_i the noise of acc is only scaled by the message of C_i and the noise of C_i
+    /// by the entries of acc (see `mult_noise_bound`). acc is flattened after every step (also
+    /// without `use_flatten`), so its entries are digits and for binary messages the noise grows
+    /// linearly: by at most N * digit_bound times the noise of C_i per step.
+    /// The product of no ciphertexts is a trivial encryption of 1.
+    pub fn mult_many(&self, ciphertexts: &[GswCiphertext<R>]) -> Result<GswCiphertext<R>, TfheError> {
+        let Some((first, rest)) = ciphertexts.split_first() else {
+            return Ok(self.trivial_encrypt(R::one()));
+        };
+        rest.iter().try_fold(self.flattened(first.clone()), |acc, ct| {
+            self.mult(&acc, ct).map(|product| self.flattened(product))
+        })
+    }
+
+    /// Applies Flatten unless the ciphertext is flattened already, C*v (and thereby the noise) is unchanged
+    fn flattened(&self, ciphertext: GswCiphertext<R>) -> GswCiphertext<R> {
+        if ciphertext.is_flattened() {
+            return ciphertext;
+        }
+        let mut matrix = ciphertext.matrix().into_owned();
+        flatten_matrix(&mut matrix, &self.gadget);
+        GswCiphertext::new(self.store_flattened(matrix), *ciphertext.fingerprint(), true, ciphertext.noise_bound(), ciphertext.message_bound())
+    }
+
+    /// AND of all encrypted bits, see `mult_many`
+    pub fn and_many(&self, ciphertexts: &[GswCiphertext<R>]) -> Result<GswCiphertext<R>, TfheError> {
+        self.mult_many(ciphertexts)
+    }
+
+    /// Like `decrypt` but fails if the noise bound of the ciphertext exceeds the decoding window
+    pub fn checked_decrypt(&self, sk: &GswSk<R>, ciphertext: &GswCiphertext<R>) -> Result<R, TfheError> {
+        Self::check_noise(ciphertext, self.noise_window())?;
+        Ok(self.decrypt(sk, ciphertext))
+    }
+
+    /// Like `mp_decrypt` but fails if the noise bound of the ciphertext exceeds the decoding window
+    pub fn checked_mp_decrypt(&self, sk: &GswSk<R>, ciphertext: &GswCiphertext<R>) -> Result<R, TfheError> {
+        Self::check_noise(ciphertext, self.mp_noise_window())?;
+        Ok(self.mp_decrypt(sk, ciphertext))
+    }
+
+    /// Bound on |mu| below which operations on messages agree with Z_t arithmetic.
+    /// If t divides q, reducing mod t commutes with the ring operations and messages may wrap around.
+    /// Otherwise the integer message must stay below q/2 so that its centered representative is exact.
+    pub fn message_window(&self) -> u64 {
+        let q = R::max_u64() as u128 + 1;
+        if q.is_multiple_of(self.message_modulus as u128) {
+            u64::MAX
+        } else {
+            R::max_u64() / 2 + 1
+        }
+    }
+
+    /// Encrypts mu in Z_t, the counterpart of `decrypt_message`.
+    /// Fails with `MessageOverflow` if mu is not in [0, t), `encrypt` would encrypt it unreduced.
+    pub fn encrypt_message(&self, pk: &GswPk<R>, message: u64) -> Result<GswCiphertext<R>, TfheError> {
+        if message >= self.message_modulus {
+            return Err(TfheError::MessageOverflow { bound: message, window: self.message_modulus });
+        }
+        Ok(self.encrypt(pk, R::from(message)))
+    }
+
+    /// Decodes mu mod t by rounding (see `mp_decrypt`) and reducing the message mod t.
+    /// Fails if the noise bound exceeds the multi-bit decoding window
+    /// or the message bound exceeds the message window.
+    pub fn decrypt_message(&self, sk: &GswSk<R>, ciphertext: &GswCiphertext<R>) -> Result<u64, TfheError> {
+        Self::check_noise(ciphertext, self.mp_noise_window())?;
+        let window = self.message_window();
+        if ciphertext.message_bound() >= window {
+            return Err(TfheError::MessageOverflow { bound: ciphertext.message_bound(), window });
+        }
+
+        let mu: u64 = self.mp_decrypt(sk, ciphertext).into();
+        let t = self.message_modulus;
+        if mu <= R::max_u64() / 2 || window == u64::MAX {
+            Ok(mu % t)
+        } else {
+            // negative centered representative mu - q
+            let abs = R::max_u64() - mu + 1;
+            Ok((t - abs % t) % t)
+        }
+    }
+}
 
 impl<R: RingElement + 'static, T: ErrorSampling<R>> FheScheme<R> for GSW<R, T> {
     type SecretKey = GswSk<R>;
     type PublicKey = GswPk<R>;
-    type Ciphertext = DMatrix<R>;
+    type Ciphertext = GswCiphertext<R>;
 
     fn keygen(&self) -> (Self::SecretKey, Self::PublicKey) {
-        let sk = GswSk::new(rnd_dvec(self.n as usize, 0, R::max_u64()));  
+        let mut sk = GswSk::new(rnd_dvec(self.n as usize, 0, R::max_u64()), &self.gadget);  
           
-        let err: DVector<R> = self.err_sampling.rnd_error_dvec(self.m as usize);    
-        let random_matrix: DMatrix<R> = rnd_dmatrix(self.m, self.n, 0, R::max_u64());
+        let err = Secret::new(self.err_sampling.rnd_error_dvec(self.m as usize));    
         
-        let pk = GswPk::new(&random_matrix, &err, &sk.t);
+        let pk = GswPk::from_seed(rnd_seed(), err.expose(), sk.t()).with_error_bound(self.err_sampling.error_bound());
+        sk.key_fingerprint = Some(pk.fingerprint());
         (sk, pk)   
     }
 
     fn encrypt(&self, pk: &Self::PublicKey, message: R) -> Self::Ciphertext {
-        let big_n: usize = (R::Num_Bits as usize) * ((self.n + 1) as usize);
+        let big_n = self.ciphertext_dim();
 
         let random_matrix = rnd_dmatrix(big_n, self.m, 0, 1);
-        let mut product = &random_matrix * &pk.pk_matrix;
-        bit_decomp_matrix(&mut product);
-        // Add message to diagonal (matrix is square)
-        for i in 0..product.ncols() {
-            product[(i, i)] += message;
-        }
-
-        #[cfg(feature="use_flatten")]
-        flatten_matrix(&mut product);
+        let product = mat_mul(&random_matrix, &pk.pk_matrix);
+        let fingerprint = Fingerprint { params: self.params_fingerprint(), key: Some(pk.fingerprint()) };
+        GswCiphertext::new(self.flatten_if_enabled(embed_message(product, message, &self.gadget)), fingerprint, cfg!(feature="use_flatten"), self.pk_noise_bound(pk), self.fresh_message_bound(message))
+    }
 
-        product
+    /// Samples N fresh LWE samples (<a_i, t> + e_i, a_i) directly under sk.t
+    /// instead of combining the rows of pk, so the noise is e instead of R*e.
+    /// The ciphertext is bound to the pk of sk if it is known (see `GswSk::key_fingerprint`).
+    fn encrypt_sk(&self, sk: &Self::SecretKey, message: R) -> Self::Ciphertext {
+        let big_n = self.ciphertext_dim();
+
+        let random_matrix: DMatrix<R> = rnd_dmatrix(big_n, self.n, 0, R::max_u64());
+        let err = Secret::new(self.err_sampling.rnd_error_dvec(big_n));
+        let lwe_samples = GswPk::new(&random_matrix, err.expose(), sk.t()).pk_matrix;
+        let fingerprint = Fingerprint { params: self.params_fingerprint(), key: sk.key_fingerprint };
+        GswCiphertext::new(self.flatten_if_enabled(embed_message(lwe_samples, message, &self.gadget)), fingerprint, cfg!(feature="use_flatten"), self.fresh_sk_noise_bound(), self.fresh_message_bound(message))
     }
 
 
     /**  
-     * sk.v[i] == 2^{i-1} bc the first entry of s is 1 by definition and v = pow2(s)
+     * sk.v()[i] == B^i bc the first entry of s is 1 by definition and v = pow2(s)
      */
     fn decrypt(&self, sk: &Self::SecretKey, ciphertext: &Self::Ciphertext) -> R {
-        let i = R::Num_Bits -1;
-        let cipher_row_dot_prod = ciphertext.row(i).transpose().dot(&sk.v);
-        is_zero_one(cipher_row_dot_prod)
+        let cipher_row_dot_prod = self.decryption_row(ciphertext).dot(sk.v());
+        round_to_bit(cipher_row_dot_prod, self.gadget.power(self.gadget.num_digits::<R>() - 1))
     }
 
 
     /**
-     * collect LSBs
-     * Let t_{l-1} denote the last element of slice. then this contains
-     * 2^{l-1} * mu + noise
-     * It holds that 
-     * 2^{l-1} * mu = mu_1 mod 2^l 
-     * where mu_1 is the LSB.
-     * Following this logic it holds that
-     * mu_2 = t_{l-2} - 2^{l-2} mu_1
-     * <==> mu_2 = t_{l-2} - recovered_bits << l-2
-     * etc.
+     * Runs `recover_message` on C*v restricted to the first l rows
      */
     fn mp_decrypt(&self, sk: &Self::SecretKey, ciphertext: &Self::Ciphertext) -> R {
-        if TypeId::of::<R>() == TypeId::of::<Fp>() {
-            panic!("Only supported for pow2 rings!");
-        } else {
-            let product = ciphertext * &sk.v;
-            let slice = &product.as_slice()[..R::Num_Bits];
-            let mut recovered_bits: u64 = 0;
-            let mut recovered_exp;
-            let mut current_exp;
-
-            for (i,entry) in slice.into_iter().rev().enumerate() {
-                recovered_exp = R::from(recovered_bits << (R::Num_Bits - i - 1));
-                current_exp = *entry - recovered_exp;
-                recovered_bits ^= (is_zero_one(current_exp).is_one() as u64) << i;
-            }
-            R::from(recovered_bits)
-        }        
+        let product = self.mp_decryption_rows(ciphertext) * sk.v();
+        recover_message(product.as_slice(), &self.gadget)
     }
 
     // flatten(C1+C2)
-    fn add(&self, ciphertext1: &Self::Ciphertext, cipertext2: &Self::Ciphertext) -> Self::Ciphertext {
-        assert_eq!(ciphertext1.nrows(), cipertext2.nrows(), "Cannot add Ciphertexts because they are different sizes");
-        assert_eq!(ciphertext1.ncols(), cipertext2.ncols(), "Cannot add Ciphertexts because they are different sizes");
-        let mut res = ciphertext1 + cipertext2;
-        #[cfg(feature="use_flatten")]
-        flatten_matrix(&mut res);
-        res
+    // e = e1 + e2
+    fn add(&self, ciphertext1: &Self::Ciphertext, cipertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError> {
+        let fingerprint = self.combine_fingerprints(ciphertext1, cipertext2)?;
+        let res = self.flatten_if_enabled(ciphertext1.matrix().as_ref() + cipertext2.matrix().as_ref());
+        Ok(GswCiphertext::new(res, fingerprint, cfg!(feature="use_flatten"),
+            ciphertext1.noise_bound().saturating_add(cipertext2.noise_bound()),
+            ciphertext1.message_bound().saturating_add(cipertext2.message_bound())))
     }
 
     // flatten(C*a)
-    fn mult_const(&self, ciphertext: &mut Self::Ciphertext, constant: R) {
-        *ciphertext *= constant;
-        #[cfg(feature="use_flatten")]
-        flatten_matrix(ciphertext);
+    // e = a*e
+    fn mult_const(&self, ciphertext: &mut Self::Ciphertext, constant: R) -> Result<(), TfheError> {
+        self.check_params(ciphertext)?;
+        let res = self.flatten_if_enabled(ciphertext.matrix().as_ref() * constant);
+        let factor = centered_abs(constant);
+        *ciphertext = GswCiphertext::new(res, *ciphertext.fingerprint(), cfg!(feature="use_flatten"),
+            ciphertext.noise_bound().saturating_mul(factor),
+            ciphertext.message_bound().saturating_mul(factor));
+        Ok(())
     }
 
     // flatten(C1*C2)
-    fn mult(&self, ciphertext1: &Self::Ciphertext, cipertext2: &Self::Ciphertext) -> Self::Ciphertext {
-        assert_eq!(ciphertext1.nrows(), cipertext2.nrows(), "Cannot add Ciphertexts because they are different sizes");
-        assert_eq!(ciphertext1.ncols(), cipertext2.ncols(), "Cannot add Ciphertexts because they are different sizes");
-        let mut res = ciphertext1 * cipertext2;
-        #[cfg(feature="use_flatten")]
-        flatten_matrix(&mut res);
-        res
+    fn mult(&self, ciphertext1: &Self::Ciphertext, cipertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError> {
+        let fingerprint = self.combine_fingerprints(ciphertext1, cipertext2)?;
+        let res = self.flatten_if_enabled(self.product(ciphertext1, cipertext2));
+        Ok(GswCiphertext::new(res, fingerprint, cfg!(feature="use_flatten"),
+            mult_noise_bound(ciphertext1, cipertext2),
+            ciphertext1.message_bound().saturating_mul(cipertext2.message_bound())))
     }
 
     // flatten(I - C1*C2)
-    fn nand(&self, ciphertext1: &Self::Ciphertext, cipertext2: &Self::Ciphertext) -> Self::Ciphertext {
-        let mut prod = ciphertext1 * cipertext2;
+    fn nand(&self, ciphertext1: &Self::Ciphertext, cipertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError> {
+        let fingerprint = self.combine_fingerprints(ciphertext1, cipertext2)?;
+        let mut prod = self.product(ciphertext1, cipertext2);
         prod.neg_mut();
         for i in 0..prod.ncols() {
             prod[(i, i)] += R::one();
         } 
-        #[cfg(feature="use_flatten")]
-        flatten_matrix(&mut prod);
-        prod
+        let prod = self.flatten_if_enabled(prod);
+        Ok(GswCiphertext::new(prod, fingerprint, cfg!(feature="use_flatten"),
+            mult_noise_bound(ciphertext1, cipertext2),
+            gate_message_bound(&[ciphertext1, cipertext2], ciphertext1.message_bound().saturating_mul(cipertext2.message_bound()).saturating_add(1))))
+    }
+
+    // flatten(I - C)
+    // e = -e
+    fn not(&self, ciphertext: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError> {
+        self.check_params(ciphertext)?;
+        let mut res = -ciphertext.matrix().into_owned();
+        for i in 0..res.ncols() {
+            res[(i, i)] += R::one();
+        }
+        let res = self.flatten_if_enabled(res);
+        Ok(GswCiphertext::new(res, *ciphertext.fingerprint(), cfg!(feature="use_flatten"),
+            ciphertext.noise_bound(),
+            gate_message_bound(&[ciphertext], ciphertext.message_bound().saturating_add(1))))
+    }
+
+    // flatten(C1*C2)
+    fn and(&self, ciphertext1: &Self::Ciphertext, cipertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError> {
+        self.mult(ciphertext1, cipertext2)
+    }
+
+    // flatten(C1 + C2 - C1*C2)
+    // e = e1 + e2 - e_mult
+    fn or(&self, ciphertext1: &Self::Ciphertext, cipertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError> {
+        let fingerprint = self.combine_fingerprints(ciphertext1, cipertext2)?;
+        let prod = self.product(ciphertext1, cipertext2);
+        let res = self.flatten_if_enabled(ciphertext1.matrix().as_ref() + cipertext2.matrix().as_ref() - prod);
+        let (mb1, mb2) = (ciphertext1.message_bound(), cipertext2.message_bound());
+        Ok(GswCiphertext::new(res, fingerprint, cfg!(feature="use_flatten"),
+            ciphertext1.noise_bound().saturating_add(cipertext2.noise_bound()).saturating_add(mult_noise_bound(ciphertext1, cipertext2)),
+            gate_message_bound(&[ciphertext1, cipertext2], mb1.saturating_add(mb2).saturating_add(mb1.saturating_mul(mb2)))))
+    }
+
+    // flatten(C1 + C2 - 2*C1*C2)
+    // e = e1 + e2 - 2*e_mult
+    fn xor(&self, ciphertext1: &Self::Ciphertext, cipertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError> {
+        let fingerprint = self.combine_fingerprints(ciphertext1, cipertext2)?;
+        let prod = self.product(ciphertext1, cipertext2);
+        let res = self.flatten_if_enabled(ciphertext1.matrix().as_ref() + cipertext2.matrix().as_ref() - prod * R::from(2));
+        let (mb1, mb2) = (ciphertext1.message_bound(), cipertext2.message_bound());
+        Ok(GswCiphertext::new(res, fingerprint, cfg!(feature="use_flatten"),
+            ciphertext1.noise_bound().saturating_add(cipertext2.noise_bound()).saturating_add(mult_noise_bound(ciphertext1, cipertext2).saturating_mul(2)),
+            gate_message_bound(&[ciphertext1, cipertext2], mb1.saturating_add(mb2).saturating_add(mb1.saturating_mul(mb2).saturating_mul(2)))))
+    }
+
+    // flatten(C0 + Cs*(C1 - C0))
+    // e = e0 + mu_d*e_s + Cs*(e1 - e0) where mu_d = mu1 - mu0
+    // The selector is the left factor, so its noise is only scaled by mu_d.
+    fn cmux(&self, selector: &Self::Ciphertext, ciphertext1: &Self::Ciphertext, ciphertext0: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError> {
+        let fingerprint = self.combine_fingerprints(ciphertext1, ciphertext0)?;
+        let fingerprint = fingerprint.combine(selector.fingerprint())?;
+        let diff = GswCiphertext::new(CiphertextMatrix::Dense(ciphertext1.matrix().as_ref() - ciphertext0.matrix().as_ref()), fingerprint, false,
+            ciphertext1.noise_bound().saturating_add(ciphertext0.noise_bound()),
+            // mu1 - mu0 is in {-1, 0, 1} for bits
+            gate_message_bound(&[ciphertext1, ciphertext0], ciphertext1.message_bound().saturating_add(ciphertext0.message_bound())));
+        let res = self.flatten_if_enabled(ciphertext0.matrix().as_ref() + self.product(selector, &diff));
+        Ok(GswCiphertext::new(res, fingerprint, cfg!(feature="use_flatten"),
+            ciphertext0.noise_bound().saturating_add(mult_noise_bound(selector, &diff)),
+            gate_message_bound(&[selector, ciphertext1, ciphertext0],
+                ciphertext0.message_bound().saturating_add(selector.message_bound().saturating_mul(diff.message_bound())))))
+    }
+
+}
+
+/// Boolean gates map bits to bits, so their output message bound is 1 if all inputs are bits.
+/// Otherwise `bound`, the bound of the gate's integer expression, is used.
+/// Without this the bounds of bits would compound over a few gates (1, 2, 5, 26, 677, ... for NAND)
+/// and inflate the noise bounds they are multiplied into.
+fn gate_message_bound<R: RingElement + 'static>(inputs: &[&GswCiphertext<R>], bound: u64) -> u64 {
+    if inputs.iter().all(|ct| ct.message_bound() <= 1) { 1 } else { bound }
+}
+
+/// GSW noise grows asymmetrically:
+/// C1*C2*v = C1*(mu2*v + e2) = mu2*mu1*v + mu2*e1 + C1*e2
+/// so e1 is only scaled by the message mu2 while e2 is scaled by the entries of C1
+/// (at most N * digit_bound if C1 is flattened)
+fn mult_noise_bound<R: RingElement + 'static>(ciphertext1: &GswCiphertext<R>, ciphertext2: &GswCiphertext<R>) -> u64 {
+    ciphertext2.message_bound().saturating_mul(ciphertext1.noise_bound())
+        .saturating_add(ciphertext1.inf_norm().saturating_mul(ciphertext2.noise_bound()))
+}
+
+/// BitDecomp(A) + mu*I for A with A*s = small
+fn embed_message<R: RingElement + 'static>(mut lwe_samples: DMatrix<R>, message: R, gadget: &Gadget) -> DMatrix<R> {
+    bit_decomp_matrix(&mut lwe_samples, gadget);
+    // Add message to diagonal (matrix is square)
+    for i in 0..lwe_samples.ncols() {
+        lwe_samples[(i, i)] += message;
+    }
+    lwe_samples
+}
+
+/// Recovers mu in [0, q) from the entries B^j * mu + e_j of the first l rows of C*v
+pub(crate) fn recover_message<R: RingElement + 'static>(slice: &[R], gadget: &Gadget) -> R {
+    if is_pow2_modulus::<R>() {
+        recover_bits(slice, gadget)
+    } else {
+        recover_by_lifting(slice, gadget)
+    }
+}
+
+/// q = max_u64 + 1 is a power of two
+fn is_pow2_modulus<R: RingElement>() -> bool {
+    R::max_u64() & R::max_u64().wrapping_add(1) == 0
+}
+
+/**
+ * For odd q, B^j * mu mod q depends on all bits of mu, so bits cannot be peeled off.
+ * Instead every row lifts an integer estimate M_{j-1} = B^{j-1} * mu + e_{j-1} to
+ * M_j = the representative of t_j closest to B * M_{j-1}.
+ * Since B^j * mu + e_j is such a representative and |B * e_{j-1} - e_j| <= (B+1)|e|,
+ * M_j = B^j * mu + e_j as long as (B+1)|e| < q/2.
+ * Finally mu = round(M_{l-1} / B^{l-1}) mod q, which is correct as long as |e| < B^{l-1}/2.
+ * Starting with M_0 = t_0 in [0, q) may lift mu + q instead of mu, which is the same mod q.
+ */
+fn recover_by_lifting<R: RingElement + 'static>(slice: &[R], gadget: &Gadget) -> R {
+    let len = gadget.num_digits::<R>();
+    assert_eq!(slice.len(), len, "Need one entry per gadget row");
+    assert!(len > 1, "Need at least two gadget rows to round");
+
+    let q = R::max_u64() as i128 + 1;
+    let base = gadget.base() as i128;
+    let mut estimate = Into::<u64>::into(slice[0]) as i128;
+    for entry in &slice[1..] {
+        let target = estimate * base;
+        let mut offset = (Into::<u64>::into(*entry) as i128 - target).rem_euclid(q);
+        if offset > q / 2 {
+            offset -= q;
+        }
+        estimate = target + offset;
     }
 
+    let scale = base.pow(len as u32 - 1);
+    let mu = (estimate + scale / 2).div_euclid(scale).rem_euclid(q);
+    R::from(mu as u64)
+}
+
+/**
+ * collect LSBs, k = log B at a time
+ * Let t_{l-1} denote the last element of slice. then this contains
+ * B^{l-1} * mu + noise
+ * It holds that 
+ * B^{l-1} * mu = mu_1 * 2^{k(l-1)} mod 2^{Num_Bits}
+ * where mu_1 are the lowest Num_Bits - k(l-1) bits of mu.
+ * Following this logic it holds that
+ * mu_2 * 2^{Num_Bits-k} = t_{l-2} - B^{l-2} mu_1
+ * <==> mu_2 = round((t_{l-2} - recovered_bits * B^{l-2}) / 2^{Num_Bits-k})
+ * etc. For B = 2 every row yields a single bit.
+ */
+fn recover_bits<R: RingElement + 'static>(slice: &[R], gadget: &Gadget) -> R {
+    let len = gadget.num_digits::<R>();
+    assert_eq!(slice.len(), len, "Need one entry per gadget row");
+    assert!(len > 1, "Need at least two gadget rows to round");
+
+    let mut recovered_bits: u64 = 0;
+    let mut num_recovered = 0;
+
+    for (j, entry) in slice.iter().enumerate().rev() {
+        // bits of mu that influence row j but not the rows above
+        let new_bits = R::Num_Bits - gadget.log_base() * j - num_recovered;
+        let shift = R::Num_Bits - new_bits;
+        let current = *entry - R::from(recovered_bits) * gadget.power(j) + R::from(1u64 << (shift - 1));
+        let digit = (Into::<u64>::into(current) >> shift) & ((1u64 << new_bits) - 1);
+        recovered_bits |= digit << num_recovered;
+        num_recovered += new_bits;
+    }
+    R::from(recovered_bits)
 }
 
-fn is_zero_one<R: RingElement>(input: R) -> R {
-    if input >= R::from(R::max_u64()/4) && input <= R::from(3*R::max_u64()/4)  {
+/// Rounds input = mu * scale + e to mu in {0, 1}, correct as long as |e| < scale/2
+pub(crate) fn round_to_bit<R: RingElement>(input: R, scale: R) -> R {
+    if centered_abs(input - scale) < centered_abs(input) {
         R::one()
     } else { R::zero() }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::marker::PhantomData;
+    use num_traits::{One, Zero};
     use rand::Rng;
 
+    use crate::error::TfheError;
     use crate::error_sampling::rnd_dmatrix;
     use crate::error_sampling::rnd_dvec;
     use crate::error_sampling::DiscrGaussianSampler;
     use crate::error_sampling::NaiveSampler;
     use crate::field::Fp;
+    use crate::gsw::ciphertext::GswCiphertext;
+    use crate::gsw::gadget::Gadget;
+    use crate::gsw::helper::centered_abs;
+    use crate::gsw::params::SecurityLevel;
     use crate::gsw::pk::GswPk;
     use crate::gsw::FheScheme;
     use crate::gsw::sk::GswSk;
@@ -152,11 +537,11 @@ mod tests {
         let n = 10;
         let m = 10 * Fp::Num_Bits;
 
-        let sk: GswSk<Fp> = GswSk::new(rnd_dvec(n, 0, Fp::max_u64()));
+        let sk: GswSk<Fp> = GswSk::new(rnd_dvec(n, 0, Fp::max_u64()), &Gadget::BINARY);
         let err = rnd_dvec(m, 0, Fp::max_u64()>>15);
         let random_matrix = rnd_dmatrix(err.len(), n, 0, Fp::max_u64());
-        let pk = GswPk::new(&random_matrix, &err, &sk.t);
-        let invariant = &pk.pk_matrix * &sk.s;
+        let pk = GswPk::new(&random_matrix, &err, sk.t());
+        let invariant = &pk.pk_matrix * sk.s();
 
         // As = e
         assert_eq!(invariant, err);
@@ -164,36 +549,21 @@ mod tests {
 
     #[test]
     fn encryption_decryption_naive() {
-        let naive_gsw = GSW::<Fp, NaiveSampler> {
-            n: 10,
-            m: 10 * Fp::Num_Bits,
-            err_sampling: NaiveSampler,
-            _marker: PhantomData,
-        };
+        let naive_gsw = GSW::<Fp, NaiveSampler>::new(10, 10 * Fp::Num_Bits, NaiveSampler, Gadget::BINARY).unwrap();
         test_inputs(naive_gsw);
     }
 
 
     #[test]
     fn encryption_decryption_discr_gaussian() {
-        let gaussian_gsw = GSW::<Zpow2<30>, DiscrGaussianSampler> {
-            n:10, 
-            m: 10*Zpow2::<30>::Num_Bits, 
-            err_sampling: DiscrGaussianSampler::default(), 
-            _marker: PhantomData
-        };
+        let gaussian_gsw = GSW::<Zpow2<30>, DiscrGaussianSampler>::new(10, 10*Zpow2::<30>::Num_Bits, DiscrGaussianSampler::default(), Gadget::BINARY).unwrap();
         test_inputs(gaussian_gsw);
     }
 
 
     #[test]
     fn encryption_decryption_pow_of_two() {
-        let fhe = GSW::<Zpow2<31>, DiscrGaussianSampler> {
-            n:10, 
-            m: 10 * Zpow2::<31>::Num_Bits, 
-            err_sampling: DiscrGaussianSampler::default(),
-            _marker: PhantomData 
-        };
+        let fhe = GSW::<Zpow2<31>, DiscrGaussianSampler>::new(10, 10 * Zpow2::<31>::Num_Bits, DiscrGaussianSampler::default(), Gadget::BINARY).unwrap();
         
         let (sk, pk) = fhe.keygen();
 
@@ -206,6 +576,295 @@ mod tests {
         }
     }
 
+    #[test]
+    fn mp_decryption_prime_modulus() {
+        for gadget in [Gadget::BINARY, Gadget::new(4, false), Gadget::new(4, true)] {
+            let fhe = GSW::<Fp, DiscrGaussianSampler>::new(4, 4 * Fp::Num_Bits, DiscrGaussianSampler::default(), gadget).unwrap();
+            let (sk, pk) = fhe.keygen();
+
+            let mut rng = rand::rng();
+            let random = (0..5).map(|_| Fp::from(rng.random_range(0..Fp::max_u64())));
+            for msg in [Fp::zero(), Fp::one(), -Fp::one()].into_iter().chain(random) {
+                assert_eq!(fhe.checked_mp_decrypt(&sk, &fhe.encrypt(&pk, msg)), Ok(msg));
+                assert_eq!(fhe.mp_decrypt(&sk, &fhe.encrypt_sk(&sk, msg)), msg);
+            }
+        }
+    }
+
+    #[test]
+    fn message_modulus() {
+        let mut rng = rand::rng();
+        for t in [4u64, 10, 16] {
+            let fhe = GSW::<Fp, DiscrGaussianSampler>::new(4, 4 * Fp::Num_Bits, DiscrGaussianSampler::default(), Gadget::BINARY)
+                .unwrap().with_message_modulus(t).unwrap();
+            let (sk, pk) = fhe.keygen();
+            let msgs: Vec<u64> = (0..3).map(|_| rng.random_range(0..t)).collect();
+            let cts: Vec<_> = msgs.iter().map(|mu| fhe.encrypt_message(&pk, *mu).unwrap()).collect();
+            assert!(cts.iter().all(|ct| ct.message_bound() == t - 1));
+
+            // counter: sum of all messages, wraps around mod t
+            let sum = cts[1..].iter().fold(cts[0].clone(), |acc, ct| fhe.add(&acc, ct).unwrap());
+            assert_eq!(fhe.decrypt_message(&sk, &sum), Ok(msgs.iter().sum::<u64>() % t));
+
+            let product = fhe.mult(&cts[0], &cts[1]).unwrap();
+            assert_eq!(fhe.decrypt_message(&sk, &product), Ok(msgs[0] * msgs[1] % t));
+
+            // 1 - mu is negative as an integer
+            assert_eq!(fhe.decrypt_message(&sk, &fhe.not(&cts[2]).unwrap()), Ok((1 + t - msgs[2]) % t));
+        }
+
+        // pow2 moduli may wrap around q
+        let fhe = GSW::<Zpow2<31>, DiscrGaussianSampler>::new(4, 4 * 31, DiscrGaussianSampler::default(), Gadget::BINARY)
+            .unwrap().with_message_modulus(16).unwrap();
+        let (sk, pk) = fhe.keygen();
+        let mut ct = fhe.encrypt(&pk, Zpow2::from(5));
+        fhe.mult_const(&mut ct, -Zpow2::one()).unwrap();
+        assert_eq!(fhe.decrypt_message(&sk, &ct), Ok(11));
+        assert_eq!(fhe.message_window(), u64::MAX);
+
+        // for odd q the message must not wrap around
+        let fhe = GSW::<Fp, DiscrGaussianSampler>::new(4, 4 * Fp::Num_Bits, DiscrGaussianSampler::default(), Gadget::BINARY)
+            .unwrap().with_message_modulus(10).unwrap();
+        let (sk, _) = fhe.keygen();
+        let mut large = fhe.trivial_encrypt(Fp::from(9));
+        fhe.mult_const(&mut large, Fp::from(Fp::max_u64() / 3)).unwrap();
+        assert!(matches!(fhe.decrypt_message(&sk, &large), Err(TfheError::MessageOverflow { .. })));
+
+        // messages outside of [0, t) are rejected by encrypt_message,
+        // encrypt keeps them unreduced and tracks their actual size
+        let (sk, pk) = fhe.keygen();
+        assert_eq!(fhe.encrypt_message(&pk, 10).err(), Some(TfheError::MessageOverflow { bound: 10, window: 10 }));
+        assert_eq!(fhe.decrypt_message(&sk, &fhe.encrypt_message(&pk, 9).unwrap()), Ok(9));
+        let out_of_range = fhe.encrypt(&pk, Fp::from(1 << 30));
+        assert_eq!(out_of_range.message_bound(), centered_abs(Fp::from(1 << 30)));
+        assert_eq!(fhe.encrypt_sk(&sk, Fp::from(1 << 30)).message_bound(), out_of_range.message_bound());
+        let sum = fhe.add(&out_of_range, &out_of_range).unwrap();
+        assert!(matches!(fhe.decrypt_message(&sk, &sum), Err(TfheError::MessageOverflow { .. })));
+        // the product fails on the noise check first, which scales with the message
+        let product = fhe.mult(&out_of_range, &out_of_range).unwrap();
+        assert!(product.message_bound() >= fhe.message_window());
+        assert!(fhe.decrypt_message(&sk, &product).is_err());
+        assert_eq!(fhe.encrypt(&pk, -Fp::one()).message_bound(), 9);
+    }
+
+    #[test]
+    fn secret_key_encryption() {
+        let fhe = GSW::<Zpow2<31>, DiscrGaussianSampler>::preset(SecurityLevel::Toy).unwrap();
+        let (sk, pk) = fhe.keygen();
+
+        let zero = fhe.encrypt_sk(&sk, Zpow2::zero());
+        let one = fhe.encrypt_sk(&sk, Zpow2::one());
+        assert_eq!(fhe.decrypt(&sk, &zero), Zpow2::zero());
+        assert_eq!(fhe.decrypt(&sk, &one), Zpow2::one());
+
+        // noise is a single error sample
+        let noise = fhe.decryption_row(&zero).dot(sk.v());
+        let abs = std::cmp::min(noise.value(), (-noise).value());
+        assert!(abs <= fhe.fresh_sk_noise_bound());
+
+        // compatible with pk ciphertexts and all operations
+        let pk_one = fhe.encrypt(&pk, Zpow2::one());
+        assert_eq!(fhe.decrypt(&sk, &fhe.nand(&one, &pk_one).unwrap()), Zpow2::zero());
+        assert_eq!(fhe.decrypt(&sk, &fhe.mult(&pk_one, &zero).unwrap()), Zpow2::zero());
+        assert_eq!(fhe.decrypt(&sk, &fhe.add(&zero, &pk_one).unwrap()), Zpow2::one());
+
+        let mut rng = rand::rng();
+        for _ in 0..5 {
+            let msg = Zpow2::<31>::from(rng.random_range(0..Zpow2::<31>::max_u64()));
+            assert_eq!(fhe.mp_decrypt(&sk, &fhe.encrypt_sk(&sk, msg)), msg);
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature="use_flatten"))]
+    fn noise_tracking() {
+        let fhe = GSW::<Zpow2<31>, DiscrGaussianSampler>::preset(SecurityLevel::Toy).unwrap();
+        let (sk, pk) = fhe.keygen();
+        let one = fhe.encrypt(&pk, Zpow2::one());
+        assert_eq!(one.noise_bound(), fhe.fresh_noise_bound());
+        assert_eq!(fhe.trivial_encrypt(Zpow2::one()).noise_bound(), 0);
+
+        let actual_noise = |ct: &GswCiphertext<Zpow2<31>>, msg: Zpow2<31>| {
+            centered_abs(fhe.decryption_row(ct).dot(sk.v()) - msg * sk.v()[Zpow2::<31>::Num_Bits - 1])
+        };
+
+        let sum = fhe.add(&one, &one).unwrap();
+        assert_eq!(sum.noise_bound(), 2 * one.noise_bound());
+
+        // NAND chain: acc <- NAND(acc, 1) = NOT acc
+        let mut acc = one.clone();
+        let mut expected = Zpow2::one();
+        for _ in 0..10 {
+            let next = fhe.nand(&acc, &one).unwrap();
+            assert!(next.noise_bound() >= acc.noise_bound());
+            acc = next;
+            expected = Zpow2::one() - expected;
+
+            if fhe.noise_budget(&acc) == 0 {
+                assert!(matches!(fhe.checked_decrypt(&sk, &acc), Err(TfheError::NoiseTooLarge { .. })));
+                return;
+            }
+            assert!(actual_noise(&acc, expected) <= acc.noise_bound());
+            assert_eq!(fhe.checked_decrypt(&sk, &acc), Ok(expected));
+        }
+        panic!("Noise budget should be exhausted without flattening");
+    }
+
+    #[test]
+    fn boolean_gates() {
+        let fhe = GSW::<Zpow2<31>, DiscrGaussianSampler>::new(4, 4 * 31, DiscrGaussianSampler::default(), Gadget::BINARY).unwrap();
+        let (sk, pk) = fhe.keygen();
+        let bits = [fhe.encrypt(&pk, Zpow2::zero()), fhe.encrypt(&pk, Zpow2::one())];
+        let actual_noise = |ct: &GswCiphertext<Zpow2<31>>, msg: bool| {
+            let msg = if msg { Zpow2::one() } else { Zpow2::zero() };
+            centered_abs(fhe.decryption_row(ct).dot(sk.v()) - msg * sk.v()[Zpow2::<31>::Num_Bits - 1])
+        };
+        let check = |ct: &GswCiphertext<Zpow2<31>>, expected: bool| {
+            assert!(actual_noise(ct, expected) <= ct.noise_bound());
+            assert_eq!(fhe.checked_decrypt(&sk, ct), Ok(if expected { Zpow2::one() } else { Zpow2::zero() }));
+        };
+
+        for a in [false, true] {
+            let ca = &bits[a as usize];
+            check(&fhe.not(ca).unwrap(), !a);
+            for b in [false, true] {
+                let cb = &bits[b as usize];
+                check(&fhe.and(ca, cb).unwrap(), a && b);
+                check(&fhe.or(ca, cb).unwrap(), a || b);
+                check(&fhe.xor(ca, cb).unwrap(), a ^ b);
+                for s in [false, true] {
+                    let cs = &bits[s as usize];
+                    check(&fhe.cmux(cs, ca, cb).unwrap(), if s { a } else { b });
+                }
+            }
+        }
+
+        // XOR costs a single multiplication instead of three levels of NAND
+        let xor = fhe.xor(&bits[0], &bits[1]).unwrap();
+        let t1 = fhe.nand(&bits[0], &bits[1]).unwrap();
+        let nand_xor = fhe.nand(&fhe.nand(&bits[0], &t1).unwrap(), &fhe.nand(&bits[1], &t1).unwrap()).unwrap();
+        assert!(xor.noise_bound() < nand_xor.noise_bound());
+
+        let (_, other_pk) = fhe.keygen();
+        let other = fhe.encrypt(&other_pk, Zpow2::one());
+        assert_eq!(fhe.cmux(&other, &bits[0], &bits[1]), Err(TfheError::IncompatibleCiphertexts));
+    }
+
+    #[test]
+    fn gate_message_bounds_stay_bits() {
+        let fhe = GSW::<Zpow2<31>, DiscrGaussianSampler>::new(4, 4 * 31, DiscrGaussianSampler::default(), Gadget::BINARY).unwrap();
+        let (sk, pk) = fhe.keygen();
+        let one = fhe.encrypt(&pk, Zpow2::one());
+        let zero = fhe.encrypt(&pk, Zpow2::zero());
+
+        // nand(acc, 1) = not(acc), the bits alternate
+        let mut acc = one.clone();
+        for _ in 0..6 {
+            acc = fhe.nand(&acc, &one).unwrap();
+            assert_eq!(acc.message_bound(), 1);
+        }
+        // the noise grows linearly with flattening, without it the unflattened acc scales the fresh noise by q
+        if cfg!(feature="use_flatten") {
+            assert_eq!(fhe.checked_decrypt(&sk, &acc), Ok(Zpow2::one()));
+        } else {
+            assert!(matches!(fhe.checked_decrypt(&sk, &acc), Err(TfheError::NoiseTooLarge { .. })));
+        }
+
+        for ct in [fhe.not(&acc).unwrap(), fhe.or(&acc, &zero).unwrap(), fhe.xor(&acc, &one).unwrap(), fhe.cmux(&acc, &zero, &one).unwrap()] {
+            assert_eq!(ct.message_bound(), 1);
+        }
+
+        // larger messages keep the bound of the integer expression
+        let fhe = fhe.with_message_modulus(4).unwrap();
+        let three = fhe.encrypt(&pk, Zpow2::from(3));
+        assert_eq!(fhe.nand(&three, &three).unwrap().message_bound(), 10);
+    }
+
+    #[test]
+    fn product_chains() {
+        let fhe = GSW::<Zpow2<31>, DiscrGaussianSampler>::new(4, 4 * 31, DiscrGaussianSampler::default(), Gadget::BINARY).unwrap();
+        let (sk, pk) = fhe.keygen();
+        let num = 8;
+        let ones: Vec<_> = (0..num).map(|_| fhe.encrypt(&pk, Zpow2::one())).collect();
+
+        let all = fhe.and_many(&ones).unwrap();
+        assert_eq!(fhe.checked_decrypt(&sk, &all), Ok(Zpow2::one()));
+
+        let mut with_zero = ones.clone();
+        with_zero[num / 2] = fhe.encrypt(&pk, Zpow2::zero());
+        assert_eq!(fhe.checked_decrypt(&sk, &fhe.and_many(&with_zero).unwrap()), Ok(Zpow2::zero()));
+
+        // the chain Flatten(acc*C_i), also without use_flatten
+        let chain = ones[1..].iter().fold(fhe.flattened(ones[0].clone()), |acc, ct| fhe.flattened(fhe.mult(&acc, ct).unwrap()));
+        assert_eq!(all.matrix(), chain.matrix());
+        assert_eq!(all.noise_bound(), chain.noise_bound());
+        assert!(all.is_flattened());
+
+        // linear growth: every step adds at most (N + 1) * fresh noise
+        let (big_n, fresh) = (fhe.ciphertext_dim() as u64, fhe.pk_noise_bound(&pk));
+        assert!(all.noise_bound() <= num as u64 * (big_n + 1) * fresh);
+        assert!(all.noise_bound() < fhe.noise_window());
+
+        assert_eq!(fhe.decrypt(&sk, &fhe.mult_many(&[]).unwrap()), Zpow2::one());
+    }
+
+    #[test]
+    fn mismatched_ciphertexts() {
+        let fhe = GSW::<Zpow2<31>, DiscrGaussianSampler>::new(4, 4 * 31, DiscrGaussianSampler::default(), Gadget::BINARY).unwrap();
+        let other_params = GSW::<Zpow2<31>, DiscrGaussianSampler>::new(4, 5 * 31, DiscrGaussianSampler::default(), Gadget::BINARY).unwrap();
+        let (sk, pk) = fhe.keygen();
+        let (_, other_pk) = fhe.keygen();
+
+        let one = fhe.encrypt(&pk, Zpow2::one());
+        let other_key = fhe.encrypt(&other_pk, Zpow2::one());
+        assert_eq!(fhe.nand(&one, &other_key), Err(TfheError::IncompatibleCiphertexts));
+        assert_eq!(fhe.add(&other_key, &one), Err(TfheError::IncompatibleCiphertexts));
+
+        let other_layout = other_params.encrypt(&other_params.keygen().1, Zpow2::one());
+        assert_eq!(fhe.mult(&one, &other_layout), Err(TfheError::IncompatibleCiphertexts));
+        assert_eq!(fhe.nand(&other_layout, &other_layout), Err(TfheError::IncompatibleCiphertexts));
+        assert_eq!(fhe.not(&other_layout), Err(TfheError::IncompatibleCiphertexts));
+        let mut scaled = other_layout.clone();
+        assert_eq!(fhe.mult_const(&mut scaled, Zpow2::one()), Err(TfheError::IncompatibleCiphertexts));
+        assert_eq!(fhe.params_fingerprint(), GSW::<Zpow2<31>, NaiveSampler>::new(4, 4 * 31, NaiveSampler, Gadget::BINARY).unwrap().params_fingerprint());
+
+        // trivial encryptions are compatible with every key, the result is bound to the key
+        let trivial = fhe.trivial_encrypt(Zpow2::one());
+        let res = fhe.nand(&trivial, &one).unwrap();
+        assert_eq!(res.fingerprint(), one.fingerprint());
+        assert_eq!(fhe.decrypt(&sk, &res), Zpow2::zero());
+        assert_eq!(one.is_flattened(), cfg!(feature="use_flatten"));
+        assert!(!trivial.is_flattened());
+
+        // secret key encryptions are bound to the pk generated alongside sk
+        assert_eq!(fhe.encrypt_sk(&sk, Zpow2::one()).fingerprint(), one.fingerprint());
+    }
+
+    #[test]
+    fn larger_gadget_base() {
+        for gadget in [Gadget::new(4, false), Gadget::new(4, true), Gadget::new(8, true)] {
+            let fhe = GSW::<Zpow2<31>, DiscrGaussianSampler>::new(4, 4 * Zpow2::<31>::Num_Bits, DiscrGaussianSampler::default(), gadget).unwrap();
+            let (sk, pk) = fhe.keygen();
+            assert_eq!(sk.v().len(), fhe.ciphertext_dim());
+            assert_eq!(fhe.ciphertext_dim(), 5 * gadget.num_digits::<Zpow2<31>>());
+
+            let zero = fhe.encrypt(&pk, Zpow2::zero());
+            let one = fhe.encrypt(&pk, Zpow2::one());
+            assert_eq!(one.matrix().nrows(), fhe.ciphertext_dim());
+            assert_eq!(fhe.checked_decrypt(&sk, &zero), Ok(Zpow2::zero()));
+            assert_eq!(fhe.checked_decrypt(&sk, &one), Ok(Zpow2::one()));
+            assert_eq!(fhe.decrypt(&sk, &fhe.nand(&one, &one).unwrap()), Zpow2::zero());
+            assert_eq!(fhe.decrypt(&sk, &fhe.nand(&zero, &one).unwrap()), Zpow2::one());
+            assert_eq!(fhe.decrypt(&sk, &fhe.mult(&one, &one).unwrap()), Zpow2::one());
+
+            let mut rng = rand::rng();
+            for _ in 0..5 {
+                let msg = Zpow2::<31>::from(rng.random_range(0..Zpow2::<31>::max_u64()));
+                assert_eq!(fhe.checked_mp_decrypt(&sk, &fhe.encrypt(&pk, msg)), Ok(msg));
+            }
+        }
+    }
+
     fn test_inputs<R: RingElement + 'static, T: FheScheme<R>>(fhe: T) {
         let (sk, pk) = fhe.keygen();
 
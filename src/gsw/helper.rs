@@ -1,67 +1,114 @@
-use crate::{RingElement};
+use crate::{gsw::gadget::Gadget, RingElement};
 use nalgebra::{DMatrix, DVector};
 
-/// BitDecomp: Expand every Fp entry into bit representation and
-/// output a.len()*L =: N-dim array of Fp::ZERO and Fp::ONE entries.
-/// in little endian.
-pub fn bit_decomp<T: RingElement>(a: &mut Vec<T>) {
-    let mut tmp = Vec::with_capacity(a.len() * T::Num_Bits);
+/// BitDecomp generalized to base B: Expand every entry into its l gadget digits and
+/// output a.len()*l =: N-dim array of small entries in little endian.
+/// For the binary gadget these are Fp::ZERO and Fp::ONE entries.
+pub fn bit_decomp<T: RingElement>(a: &mut Vec<T>, gadget: &Gadget) {
+    let mut tmp = Vec::with_capacity(a.len() * gadget.num_digits::<T>());
     for elm in a.drain(..) {
-        tmp.extend(elm.to_le_bits_re().iter().take(T::Num_Bits).map(|b| if *b { T::one() } else { T::zero() }));
+        gadget.decompose(elm, &mut tmp);
     }
     *a = tmp;
 }
 
 /// "When A is a matrix, let BitDecomp(A), BitDecomp−1 , or Flatten(A) be 
 /// the matrix formed by applying the operation to each row of A separately"
-pub fn bit_decomp_matrix<T: RingElement + 'static>(a_matrix: &mut DMatrix<T>) {
-    let mut vec_of_vec = dmatrix_to_vec_of_vecs(a_matrix);
-    vec_of_vec.iter_mut().for_each(|row| bit_decomp(row));
-    *a_matrix = vec_of_vecs_to_dmatrix(&vec_of_vec);
-}
-
-/// Only supports inverses up to u64!
-pub fn bit_decomp_inv<T: RingElement>(bits: &mut Vec<T>) {
-    let out_len = bits.len() / T::Num_Bits;
-    let mut tmp = Vec::with_capacity(out_len);
-
-    for chunk in bits.chunks(T::Num_Bits) {
-        let mut repr: u64 = 0;
-
-        for (i, bit) in chunk.iter().enumerate() {
-            if *bit == T::one() {
-                repr |= 1u64 << i;
-            }
-        }
-
-        tmp.push(T::from(repr));
+/// Column j of A becomes columns j*l..(j+1)*l of the result (storage is column major).
+pub fn bit_decomp_matrix<T: RingElement + 'static>(a_matrix: &mut DMatrix<T>, gadget: &Gadget) {
+    let len = gadget.num_digits::<T>();
+    let nrows = a_matrix.nrows();
+    let mut out = DMatrix::zeros(nrows, a_matrix.ncols() * len);
+    let mut digits = vec![T::zero(); nrows * len];
+    let mut entry_digits = vec![T::zero(); len];
+
+    for (j, column) in a_matrix.column_iter().enumerate() {
+        decompose_column(column.iter(), &mut digits, &mut entry_digits, gadget);
+        copy_digit_columns(&digits, &mut out, j * len, len);
     }
+    *a_matrix = out;
+}
 
-    *bits = tmp;
+/// BitDecomp^-1: sum_i a_i B^i over every chunk of l entries.
+/// Also defined for entries that are not digits (as needed by Flatten).
+pub fn bit_decomp_inv<T: RingElement>(bits: &mut Vec<T>, gadget: &Gadget) {
+    *bits = bits.chunks(gadget.num_digits::<T>())
+        .map(|chunk| gadget.compose(chunk))
+        .collect();
 }
 
 /// "When A is a matrix, let BitDecomp(A), BitDecomp−1 , or Flatten(A) be 
 /// the matrix formed by applying the operation to each row of A separately"
-pub fn bit_decomp_inv_matrix<T: RingElement + 'static>(a_matrix: &mut DMatrix<T>) {
-    let mut vec_of_vec = dmatrix_to_vec_of_vecs(a_matrix);
-    vec_of_vec.iter_mut().for_each(|row| bit_decomp_inv(row));
-    *a_matrix = vec_of_vecs_to_dmatrix(&vec_of_vec);
+/// Column j of the result is sum_d B^d * column j*l+d of A.
+pub fn bit_decomp_inv_matrix<T: RingElement + 'static>(a_matrix: &mut DMatrix<T>, gadget: &Gadget) {
+    let len = gadget.num_digits::<T>();
+    assert_eq!(a_matrix.ncols() % len, 0, "Number of columns must be a multiple of the gadget length");
+    let mut out = DMatrix::zeros(a_matrix.nrows(), a_matrix.ncols() / len);
+
+    for (j, mut column) in out.column_iter_mut().enumerate() {
+        compose_columns(a_matrix, j * len, gadget, column.as_mut_slice());
+    }
+    *a_matrix = out;
 }
 
-pub fn flatten<T: RingElement>(bits: &mut Vec<T>) {
-    bit_decomp_inv(bits);
-    bit_decomp(bits);
+pub fn flatten<T: RingElement>(bits: &mut Vec<T>, gadget: &Gadget) {
+    bit_decomp_inv(bits, gadget);
+    bit_decomp(bits, gadget);
 }
 
 // "When A is a matrix, let BitDecomp(A), BitDecomp−1 , or Flatten(A) be 
 // the matrix formed by applying the operation to each row of A separately" 
-pub fn flatten_matrix<T: RingElement + 'static>(a_matrix: &mut DMatrix<T>) {
-    let mut vec_of_vec = dmatrix_to_vec_of_vecs(a_matrix);
-    vec_of_vec.iter_mut().for_each(|row| flatten(row));
-    *a_matrix = vec_of_vecs_to_dmatrix(&vec_of_vec);
+/// Works in place block by block (l columns at a time), only allocating
+/// one composed column, one block of digits and the digits of one entry.
+pub fn flatten_matrix<T: RingElement + 'static>(a_matrix: &mut DMatrix<T>, gadget: &Gadget) {
+    let len = gadget.num_digits::<T>();
+    assert_eq!(a_matrix.ncols() % len, 0, "Number of columns must be a multiple of the gadget length");
+    let nrows = a_matrix.nrows();
+    let mut composed = vec![T::zero(); nrows];
+    let mut digits = vec![T::zero(); nrows * len];
+    let mut entry_digits = vec![T::zero(); len];
+
+    for block in 0..a_matrix.ncols() / len {
+        compose_columns(a_matrix, block * len, gadget, &mut composed);
+        decompose_column(composed.iter(), &mut digits, &mut entry_digits, gadget);
+        copy_digit_columns(&digits, a_matrix, block * len, len);
+    }
+}
+
+/// out = sum_d B^d * column first+d of A
+fn compose_columns<T: RingElement + 'static>(a_matrix: &DMatrix<T>, first: usize, gadget: &Gadget, out: &mut [T]) {
+    out.fill(T::zero());
+    for d in 0..gadget.num_digits::<T>() {
+        let power = gadget.power::<T>(d);
+        for (o, x) in out.iter_mut().zip(a_matrix.column(first + d).iter()) {
+            *o += *x * power;
+        }
+    }
+}
+
+/// Decomposes every entry of a column, digit d of entry i goes to digits[d * nrows + i].
+/// `entry_digits` is scratch space for the l digits of one entry, reused across columns.
+fn decompose_column<'a, T: RingElement + 'a>(column: impl ExactSizeIterator<Item = &'a T>, digits: &mut [T], entry_digits: &mut [T], gadget: &Gadget) {
+    let nrows = column.len();
+    for (i, x) in column.enumerate() {
+        gadget.decompose_into(*x, entry_digits);
+        for (d, digit) in entry_digits.iter().enumerate() {
+            digits[d * nrows + i] = *digit;
+        }
+    }
+}
+
+/// Copies the digit columns produced by `decompose_column` into columns first..first+len
+fn copy_digit_columns<T: RingElement + 'static>(digits: &[T], a_matrix: &mut DMatrix<T>, first: usize, len: usize) {
+    if a_matrix.nrows() == 0 {
+        return;
+    }
+    for (d, digit_column) in digits.chunks(a_matrix.nrows()).take(len).enumerate() {
+        a_matrix.column_mut(first + d).copy_from_slice(digit_column);
+    }
 }
 
-/// PowersOf2: (b_1, 2b_1, ..., 2^{l-1}b_1, ..., b_k, ..., 2^{l-1}b_k)
+/// PowersOf2: (b_1, Bb_1, ..., B^{l-1}b_1, ..., b_k, ..., B^{l-1}b_k) for the gadget vector (1, B, ..., B^{l-1})
 pub fn powers_of_2<T: RingElement + 'static>(b: &DVector<T>, gadget_vector: &DVector<T>) -> DVector<T> {
     let mut out = DVector::from_element(b.len() * gadget_vector.len(), T::zero()); // Initialize with zero
 
@@ -76,6 +123,19 @@ pub fn powers_of_2<T: RingElement + 'static>(b: &DVector<T>, gadget_vector: &DVe
     out
 }
 
+/// |x| for the representative of x in (-q/2, q/2]
+pub fn centered_abs<T: RingElement>(x: T) -> u64 {
+    std::cmp::min(x.into(), (-x).into())
+}
+
+/// Infinity norm max_i sum_j |a_ij|, s.t. |A*e|_inf <= inf_norm(A) * |e|_inf
+pub fn inf_norm<T: RingElement + 'static>(a_matrix: &DMatrix<T>) -> u64 {
+    a_matrix.row_iter()
+        .map(|row| row.iter().fold(0u64, |acc, x| acc.saturating_add(centered_abs(*x))))
+        .max()
+        .unwrap_or(0)
+}
+
 
 pub fn dmatrix_to_vec_of_vecs<T: RingElement>(matrix: &DMatrix<T>) -> Vec<Vec<T>> {
     let nrows = matrix.nrows();
@@ -104,7 +164,7 @@ pub fn vec_of_vecs_to_dmatrix<T: RingElement + Clone + 'static>(vec_of_vecs: &Ve
 
 #[cfg(test)]
 mod tests {
-    use crate::{error_sampling::rnd_dmatrix, field::Fp, gsw::build_gadget_vector};
+    use crate::{error_sampling::rnd_dmatrix, field::Fp, gsw::build_gadget_vector, pow2_ring::Zpow2};
     use rand::{Rng};
     use super::*;
 
@@ -125,35 +185,34 @@ mod tests {
             let input: Vec<Fp> = (0..10).map(|_| Fp::from(rng.random::<u64>())).collect();
 
             let mut decomposed = input.clone();
-            bit_decomp(&mut decomposed);
+            bit_decomp(&mut decomposed, &Gadget::BINARY);
             assert_ne!(decomposed, input);
             assert_eq!(decomposed.len(), input.len()*Fp::Num_Bits);
 
             let mut reconstructed = decomposed.clone();
-            bit_decomp_inv(&mut reconstructed);
+            bit_decomp_inv(&mut reconstructed, &Gadget::BINARY);
             assert_eq!(reconstructed.len(), input.len());
             assert_eq!(input, reconstructed);
 
             let matrix = vec_of_vecs_to_dmatrix(&vec![vec![Fp::from(2u64),Fp::from(2u64)],vec![Fp::from(2u64),Fp::from(2u64)]]);
             let mut decomp_matrix = matrix.clone();
-            bit_decomp_matrix(&mut decomp_matrix);
+            bit_decomp_matrix(&mut decomp_matrix, &Gadget::BINARY);
             assert_ne!(matrix, decomp_matrix);
-            bit_decomp_inv_matrix(&mut decomp_matrix);
+            bit_decomp_inv_matrix(&mut decomp_matrix, &Gadget::BINARY);
             assert_eq!(matrix, decomp_matrix);
         }
     }
 
-    #[test]
-    fn test_scalar_product_invariant() {
+    fn scalar_product_invariant<R: RingElement + 'static>(gadget: &Gadget) {
         let mut rng = rand::rng();
         
         for _ in 0..10{
-            let a: Vec<Fp> = (0..10).map(|_| Fp::from(rng.random::<u64>())).collect();
-            let b: Vec<Fp> = (0..10).map(|_| Fp::from(rng.random::<u64>())).collect();
+            let a: Vec<R> = (0..10).map(|_| R::from(rng.random_range(0..=R::max_u64()))).collect();
+            let b: Vec<R> = (0..10).map(|_| R::from(rng.random_range(0..=R::max_u64()))).collect();
 
             let mut bd_a = a.clone();
-            bit_decomp(&mut bd_a);
-            let po2_b = powers_of_2(&DVector::from_vec(b.clone()), &build_gadget_vector());
+            bit_decomp(&mut bd_a, gadget);
+            let po2_b = powers_of_2(&DVector::from_vec(b.clone()), &build_gadget_vector(gadget));
             assert_eq!(bd_a.len(), po2_b.len(), "Length mismatch in decomp vs powers_of_two.");
 
             let dot_decomp = DVector::from_vec(bd_a).dot(&po2_b);
@@ -163,6 +222,61 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_scalar_product_invariant() {
+        for gadget in [Gadget::BINARY, Gadget::new(4, false), Gadget::new(5, true)] {
+            scalar_product_invariant::<Fp>(&gadget);
+            scalar_product_invariant::<Zpow2<31>>(&gadget);
+        }
+    }
+
+    #[test]
+    fn test_flatten_keeps_products() {
+        // Flatten(A)*v = A*v also for entries that are not digits
+        for gadget in [Gadget::BINARY, Gadget::new(3, true)] {
+            let a: DMatrix<Zpow2<31>> = rnd_dmatrix(4, 2 * gadget.num_digits::<Zpow2<31>>(), 0, Zpow2::<31>::max_u64());
+            let v = powers_of_2(&DVector::from_vec(vec![Zpow2::from(5u64), Zpow2::from(77u64)]), &build_gadget_vector(&gadget));
+            let mut flat = a.clone();
+            flatten_matrix(&mut flat, &gadget);
+            assert!(flat.iter().all(|x| centered_abs(*x) <= gadget.digit_bound()));
+            assert_eq!(&flat * &v, &a * &v);
+        }
+    }
+
+    #[test]
+    fn test_matrix_ops_match_rows() {
+        // the column-wise matrix operations agree with applying the vector operations to every row
+        for gadget in [Gadget::BINARY, Gadget::new(4, false), Gadget::new(3, true)] {
+            let len = gadget.num_digits::<Zpow2<31>>();
+            let a: DMatrix<Zpow2<31>> = rnd_dmatrix(7, 3 * len, 0, Zpow2::<31>::max_u64());
+            let rows = dmatrix_to_vec_of_vecs(&a);
+            let apply = |op: fn(&mut Vec<Zpow2<31>>, &Gadget)| {
+                let mut rows = rows.clone();
+                rows.iter_mut().for_each(|row| op(row, &gadget));
+                vec_of_vecs_to_dmatrix(&rows)
+            };
+
+            let mut decomposed = a.clone();
+            bit_decomp_matrix(&mut decomposed, &gadget);
+            assert_eq!(decomposed, apply(bit_decomp));
+
+            let mut composed = a.clone();
+            bit_decomp_inv_matrix(&mut composed, &gadget);
+            assert_eq!(composed, apply(bit_decomp_inv));
+
+            let mut flat = a.clone();
+            flatten_matrix(&mut flat, &gadget);
+            assert_eq!(flat, apply(flatten));
+        }
+    }
+
+    #[test]
+    fn test_inf_norm() {
+        let matrix = vec_of_vecs_to_dmatrix(&vec![vec![Fp::from(2u64), -Fp::from(3u64)], vec![Fp::from(1u64), Fp::from(1u64)]]);
+        assert_eq!(centered_abs(-Fp::from(3u64)), 3);
+        assert_eq!(inf_norm(&matrix), 5);
+    }
+
     // #[test]
     // fn test_matrix_matrix_multiplication_fp() {
     //     let a = vec![
@@ -0,0 +1,160 @@
+use nalgebra::DVector;
+
+use crate::{gsw::{build_gadget_vector, gadget::Gadget, helper::powers_of_2, sk::GswSk}, secret::Secret, zo_sss::{Party, SecretSharingScheme}, RingElement};
+
+
+/// A party's share of the secret key vector v = powers_of_2(s).
+/// shares[j] is the j-th share the party holds (share id share_ids[j])
+/// and has one entry per coordinate of v.
+/// Since decryption is linear in v, <row, shares[j]> is this party's
+/// contribution to <row, v>.
+/// Shares are wiped on drop and redacted in `Debug` output.
+#[derive(Debug, Clone)]
+pub struct KeyShare<R: RingElement + 'static> {
+    pub name: u8,
+    pub share_ids: Vec<u8>,
+    shares: Secret<Vec<DVector<R>>>,
+}
+
+impl<R: RingElement + 'static> KeyShare<R> {
+    pub fn new(name: u8, share_ids: Vec<u8>, shares: Vec<DVector<R>>) -> Self {
+        Self { name, share_ids, shares: Secret::new(shares) }
+    }
+
+    pub fn shares(&self) -> &[DVector<R>] {
+        self.shares.expose()
+    }
+
+    /// Secret shares every coordinate of sk.v
+    pub fn share<S: SecretSharingScheme<R>>(sk: &GswSk<R>, sss: &S) -> Vec<Self> {
+        sss.share(sk.v().iter().copied().collect())
+            .into_iter()
+            .map(Self::from)
+            .collect()
+    }
+
+    /// Reconstructs v from a qualified set of key shares
+    pub fn combine<S: SecretSharingScheme<R>>(key_shares: &[Self], sss: &S) -> Secret<DVector<R>> {
+        let parties: Vec<Party<R>> = key_shares.iter().map(Party::from).collect();
+        Secret::new(DVector::from_vec(sss.combine(parties, false)))
+    }
+
+    /// Turns a party's shares of s into its shares of v = powers_of_2(s).
+    /// Works share by share since powers_of_2 is linear.
+    pub fn from_s_shares(s_shares: Party<R>, gadget: &Gadget) -> Self {
+        let mut key_share = Self::from(s_shares);
+        let gadget_vector = build_gadget_vector(gadget);
+        for w in key_share.shares.expose_mut().iter_mut() {
+            let expanded = powers_of_2(w, &gadget_vector);
+            // the replaced shares of s are wiped on drop
+            Secret::new(std::mem::replace(w, expanded));
+        }
+        key_share
+    }
+
+    /// Run by every party at the start of a refresh epoch.
+    /// Deals a fresh sharing of zero for every coordinate of s (`dim` = n + 1).
+    pub fn refresh_deal<S: SecretSharingScheme<R>>(dim: usize, sss: &S) -> Vec<Party<R>> {
+        sss.share(vec![R::zero(); dim])
+    }
+
+    /// Adds the zero-sharings received from all parties (one deal per party).
+    /// The shared v does not change, but shares of different epochs no longer fit together.
+    pub fn refresh(&mut self, deals: &[Party<R>], gadget: &Gadget) {
+        let (first, rest) = deals.split_first().expect("Need at least one deal");
+        let zero_shares = rest.iter().fold(first.clone(), |mut acc, deal| {
+            acc.add_shares(deal);
+            acc
+        });
+        self.add_shares(&Self::from_s_shares(zero_shares, gadget));
+    }
+
+    /// Adds another sharing held by the same party, share by share (matched by share id)
+    pub fn add_shares(&mut self, other: &KeyShare<R>) {
+        assert_eq!(self.name, other.name, "Can only add shares of the same party");
+        for (id, w) in self.share_ids.iter().zip(self.shares.expose_mut().iter_mut()) {
+            let k = other.share_ids.iter().position(|o| o == id).expect("Share ids must match");
+            *w += &other.shares()[k];
+        }
+    }
+
+    /// <row, w_j> for every share w_j held
+    pub fn dot(&self, row: &DVector<R>) -> Vec<R> {
+        self.shares().iter()
+            .map(|w| {
+                assert_eq!(row.len(), w.len(), "Key share must cover every entry of v");
+                row.dot(w)
+            })
+            .collect()
+    }
+}
+
+/// Transposes Party::shares[coordinate][j] into KeyShare::shares[j][coordinate]
+impl<R: RingElement + 'static> From<Party<R>> for KeyShare<R> {
+    fn from(party: Party<R>) -> Self {
+        let shares = (0..party.share_ids.len())
+            .map(|j| DVector::from_iterator(party.shares().len(), party.shares().iter().map(|w| w[j])))
+            .collect();
+        Self::new(party.name, party.share_ids.clone(), shares)
+    }
+}
+
+impl<R: RingElement + 'static> From<&KeyShare<R>> for Party<R> {
+    fn from(key_share: &KeyShare<R>) -> Self {
+        let dim = key_share.shares().first().map_or(0, |w| w.len());
+        let shares = (0..dim)
+            .map(|i| key_share.shares().iter().map(|w| w[i]).collect())
+            .collect();
+        Self::new(key_share.name, key_share.share_ids.clone(), shares)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{error_sampling::rnd_dvec, field::Fp, gsw::{gadget::Gadget, key_share::KeyShare, sk::GswSk}, zo_sss::{dimacs::{DIMACS, DIMACS_2_OF_3_SCHEME}, Party, MBF}, RingElement};
+
+    #[test]
+    fn share_and_combine_sk() {
+        let sk: GswSk<Fp> = GswSk::new(rnd_dvec(3, 0, Fp::max_u64()), &Gadget::BINARY);
+        let sss = MBF::new(DIMACS::parse(DIMACS_2_OF_3_SCHEME));
+
+        let key_shares = KeyShare::share(&sk, &sss);
+        assert_eq!(key_shares.len(), 3);
+        for ks in key_shares.iter() {
+            assert_eq!(ks.shares().len(), ks.share_ids.len());
+            assert!(ks.shares().iter().all(|w| w.len() == sk.v().len()));
+            // conversion is lossless
+            assert_eq!(KeyShare::from(Party::from(ks)).shares(), ks.shares());
+        }
+
+        assert_eq!(KeyShare::combine(&key_shares, &sss).expose(), sk.v());
+        assert_eq!(KeyShare::combine(&key_shares[1..], &sss).expose(), sk.v());
+    }
+
+    #[test]
+    fn refresh_keeps_secret() {
+        let sk: GswSk<Fp> = GswSk::new(rnd_dvec(3, 0, Fp::max_u64()), &Gadget::BINARY);
+        let sss = MBF::new(DIMACS::parse(DIMACS_2_OF_3_SCHEME));
+        let old = KeyShare::share(&sk, &sss);
+
+        let deals: Vec<Vec<Party<Fp>>> = (0..3).map(|_| KeyShare::refresh_deal(4, &sss)).collect();
+        let mut new = old.clone();
+        for ks in new.iter_mut() {
+            let received: Vec<Party<Fp>> = deals.iter()
+                .flat_map(|d| d.iter().filter(|p| p.name == ks.name).cloned())
+                .collect();
+            ks.refresh(&received, &Gadget::BINARY);
+        }
+
+        for (o, n) in old.iter().zip(new.iter()) {
+            assert_ne!(o.shares(), n.shares());
+        }
+        assert_eq!(KeyShare::combine(&new, &sss).expose(), sk.v());
+        assert_eq!(KeyShare::combine(&new[..2], &sss).expose(), sk.v());
+
+        // shares of different epochs cannot be combined
+        let mixed = [old[0].clone(), new[1].clone()];
+        assert_ne!(KeyShare::combine(&mixed, &sss).expose(), sk.v());
+    }
+}
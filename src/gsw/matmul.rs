@@ -0,0 +1,89 @@
+use std::ops::Range;
+
+use nalgebra::DMatrix;
+
+use crate::RingElement;
+
+
+/// Matrices with fewer rows are multiplied on the calling thread
+pub const MIN_PARALLEL_ROWS: usize = 64;
+
+/// A*B. With the `parallel` feature the rows of A are split into one block per
+/// available thread and the blocks are multiplied concurrently.
+/// Ring arithmetic is exact, so the result is identical to the serial product.
+pub fn mat_mul<R: RingElement + 'static>(a: &DMatrix<R>, b: &DMatrix<R>) -> DMatrix<R> {
+    assert_eq!(a.ncols(), b.nrows(), "Dimension mismatch");
+    #[cfg(feature="parallel")]
+    if a.nrows() >= MIN_PARALLEL_ROWS {
+        return par_mat_mul(a, b);
+    }
+    a * b
+}
+
+#[cfg(feature="parallel")]
+fn par_mat_mul<R: RingElement + 'static>(a: &DMatrix<R>, b: &DMatrix<R>) -> DMatrix<R> {
+    let blocks = row_blocks(a.nrows());
+    let products: Vec<DMatrix<R>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = blocks.iter()
+            .map(|rows| scope.spawn(move || a.rows(rows.start, rows.len()) * b))
+            .collect();
+        handles.into_iter().map(|handle| handle.join().expect("Matrix product thread panicked")).collect()
+    });
+
+    let mut out = DMatrix::zeros(a.nrows(), b.ncols());
+    for (rows, product) in blocks.iter().zip(products) {
+        out.rows_mut(rows.start, rows.len()).copy_from(&product);
+    }
+    out
+}
+
+/// Calls f(rows, chunk) for consecutive row blocks of the row major buffer `out`
+/// with rows of length `row_len`, on separate threads with the `parallel` feature.
+pub(crate) fn for_each_row_block<T: Send>(out: &mut [T], row_len: usize, f: impl Fn(Range<usize>, &mut [T]) + Sync) {
+    let nrows = out.len().checked_div(row_len).unwrap_or(0);
+    #[cfg(feature="parallel")]
+    if nrows >= MIN_PARALLEL_ROWS {
+        let blocks = row_blocks(nrows);
+        let block_len = blocks[0].len() * row_len;
+        let f = &f;
+        std::thread::scope(|scope| {
+            for (rows, chunk) in blocks.into_iter().zip(out.chunks_mut(block_len)) {
+                scope.spawn(move || f(rows, chunk));
+            }
+        });
+        return;
+    }
+    f(0..nrows, out)
+}
+
+/// Splits 0..nrows into one block of consecutive rows per available thread
+#[cfg(feature="parallel")]
+fn row_blocks(nrows: usize) -> Vec<Range<usize>> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let block = nrows.div_ceil(threads).max(1);
+    (0..nrows).step_by(block).map(|start| start..(start + block).min(nrows)).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DMatrix;
+
+    use crate::{error_sampling::rnd_dmatrix, field::Fp, gsw::{matmul::{mat_mul, MIN_PARALLEL_ROWS}, packed::PackedBitMatrix}, pow2_ring::Zpow2, RingElement};
+
+    #[test]
+    fn matches_serial_product() {
+        for nrows in [1, MIN_PARALLEL_ROWS - 1, MIN_PARALLEL_ROWS, 3 * MIN_PARALLEL_ROWS + 5] {
+            let a: DMatrix<Zpow2<31>> = rnd_dmatrix(nrows, 70, 0, Zpow2::<31>::max_u64());
+            let b: DMatrix<Zpow2<31>> = rnd_dmatrix(70, 33, 0, Zpow2::<31>::max_u64());
+            assert_eq!(mat_mul(&a, &b), &a * &b);
+
+            let bits: DMatrix<Zpow2<31>> = rnd_dmatrix(nrows, 70, 0, 1);
+            assert_eq!(PackedBitMatrix::pack(&bits).unwrap().mul_dense(&b), &bits * &b);
+        }
+
+        let a: DMatrix<Fp> = rnd_dmatrix(2 * MIN_PARALLEL_ROWS, 20, 0, Fp::max_u64());
+        let b: DMatrix<Fp> = rnd_dmatrix(20, 10, 0, Fp::max_u64());
+        assert_eq!(mat_mul(&a, &b), &a * &b);
+    }
+}
@@ -1,13 +1,20 @@
 use std::marker::PhantomData;
 
-use nalgebra::{DMatrix, DVector};
+use nalgebra::DVector;
 
-use crate::{error_sampling::{ErrorSampling, NaiveSampler}, field::{Fp}, RingElement};
+use crate::{error::TfheError, error_sampling::ErrorSampling, gsw::gadget::Gadget, RingElement};
 
+pub mod ciphertext;
+pub mod gadget;
 pub mod sk;
 pub mod pk;
 pub mod helper;
 pub mod gsw_impl;
+pub mod key_share;
+pub mod params;
+pub mod matmul;
+pub mod packed;
+pub mod sanitize;
 
 pub trait FheScheme<R: RingElement> {
     type SecretKey;
@@ -16,47 +23,42 @@ pub trait FheScheme<R: RingElement> {
 
     fn keygen(&self) -> (Self::SecretKey, Self::PublicKey);
     fn encrypt(&self, pk: &Self::PublicKey, message: R) -> Self::Ciphertext;
+    fn encrypt_sk(&self, sk: &Self::SecretKey, message: R) -> Self::Ciphertext;
     fn decrypt(&self, sk: &Self::SecretKey, ciphertext: &Self::Ciphertext) -> R;
     fn mp_decrypt(&self, sk: &Self::SecretKey, ciphertext: &Self::Ciphertext) -> R;
 
-    fn add(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Self::Ciphertext;
-    fn mult_const(&self, ciphertext: &mut Self::Ciphertext, constant: R);
-    fn mult(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Self::Ciphertext;
-    fn nand(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Self::Ciphertext;
+    /// Binary operations fail if the ciphertexts belong to different keys or parameter sets,
+    /// unary operations if the ciphertext belongs to a different parameter set
+    fn add(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError>;
+    fn mult_const(&self, ciphertext: &mut Self::Ciphertext, constant: R) -> Result<(), TfheError>;
+    fn mult(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError>;
+    fn nand(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError>;
+
+    /// Boolean gates on encrypted bits
+    fn not(&self, ciphertext: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError>;
+    fn and(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError>;
+    fn or(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError>;
+    fn xor(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError>;
+    /// Encrypts mu1 if the selector encrypts 1 and mu0 if it encrypts 0
+    fn cmux(&self, selector: &Self::Ciphertext, ciphertext1: &Self::Ciphertext, ciphertext0: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError>;
 }
 
 
+/// Use `GSW::new` or `GSW::preset` to construct validated parameters.
+/// Messages are elements of Z_t for the message modulus t (2 by default, see `GSW::with_message_modulus`).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GSW<R,T>
 where 
  R: RingElement, T: ErrorSampling<R> 
 {
-    n: usize,
-    m: usize,
-    err_sampling: T,
-    _marker: PhantomData<R>
+    pub(crate) n: usize,
+    pub(crate) m: usize,
+    pub(crate) err_sampling: T,
+    pub(crate) gadget: Gadget,
+    pub(crate) message_modulus: u64,
+    pub(crate) _marker: PhantomData<R>
 }
 
-const EXAMPLE_GSW: GSW<Fp, NaiveSampler> = GSW::<Fp, NaiveSampler> {
-        n: 10,
-        m: 10 * Fp::Num_Bits,
-        err_sampling: NaiveSampler,
-        _marker: PhantomData,
-    };
-
-fn build_gadget_matrix<R: RingElement + 'static>(n: usize, m: usize) -> DMatrix<R> {
-    // Result of Boneh's definition
-    assert!(m%R::Num_Bits == 0, 
-        "m must be a multiple of R::Num_Bits"); 
-    
-    let gadget_vec = build_gadget_vector().transpose();
-    println!("{:?}", gadget_vec);
-    // Kronecker Product: nxn \cdot 1xNum_Bits = n*1 x n * Num_bits = nxm
-    DMatrix::identity(n, n).kronecker(&gadget_vec)
+fn build_gadget_vector<R: RingElement + 'static>(gadget: &Gadget) -> DVector<R> {
+    gadget.vector()
 }
-
-fn build_gadget_vector<R: RingElement + 'static>() -> DVector<R> {
-    DVector::from_vec((0..R::Num_Bits)
-        .map(|l| R::from(1u64 << l))
-        .collect())
-}
\ No newline at end of file
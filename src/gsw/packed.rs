@@ -0,0 +1,164 @@
+use nalgebra::DMatrix;
+
+use crate::{gsw::matmul::for_each_row_block, RingElement};
+
+
+/// Row major matrix with entries in {0, 1}, stored as one bit per entry.
+/// Row i occupies limbs[i * limbs_per_row..(i + 1) * limbs_per_row],
+/// entry (i, j) is bit j % 64 of limb j / 64 of that row.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PackedBitMatrix {
+    nrows: usize,
+    ncols: usize,
+    limbs_per_row: usize,
+    limbs: Vec<u64>,
+}
+
+impl PackedBitMatrix {
+    /// Packs the matrix, `None` if any entry is neither zero nor one
+    pub fn pack<R: RingElement + 'static>(matrix: &DMatrix<R>) -> Option<Self> {
+        let (nrows, ncols) = matrix.shape();
+        let limbs_per_row = ncols.div_ceil(64);
+        let mut limbs = vec![0u64; nrows * limbs_per_row];
+
+        for (j, column) in matrix.column_iter().enumerate() {
+            let (limb, bit) = (j / 64, j % 64);
+            for (i, x) in column.iter().enumerate() {
+                if *x == R::one() {
+                    limbs[i * limbs_per_row + limb] |= 1 << bit;
+                } else if *x != R::zero() {
+                    return None;
+                }
+            }
+        }
+        Some(Self { nrows, ncols, limbs_per_row, limbs })
+    }
+
+    pub fn unpack<R: RingElement + 'static>(&self) -> DMatrix<R> {
+        self.unpack_rows(0, self.nrows)
+    }
+
+    /// Rows start..start + count as a dense matrix
+    pub fn unpack_rows<R: RingElement + 'static>(&self, start: usize, count: usize) -> DMatrix<R> {
+        assert!(start + count <= self.nrows, "Index out of bounds");
+        DMatrix::from_fn(count, self.ncols, |i, j| if self.get(start + i, j) { R::one() } else { R::zero() })
+    }
+
+    pub fn nrows(&self) -> usize {
+        self.nrows
+    }
+
+    pub fn ncols(&self) -> usize {
+        self.ncols
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> bool {
+        assert!(i < self.nrows && j < self.ncols, "Index out of bounds");
+        (self.row(i)[j / 64] >> (j % 64)) & 1 == 1
+    }
+
+    /// Largest number of ones in a row, i.e. the infinity norm
+    pub fn max_row_weight(&self) -> u64 {
+        (0..self.nrows)
+            .map(|i| self.row(i).iter().map(|limb| limb.count_ones() as u64).sum())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Number of bytes used for the entries
+    pub fn size_in_bytes(&self) -> usize {
+        self.limbs.len() * size_of::<u64>()
+    }
+
+    fn row(&self, i: usize) -> &[u64] {
+        &self.limbs[i * self.limbs_per_row..(i + 1) * self.limbs_per_row]
+    }
+
+    /// self * dense via masked accumulation: row i of the result is the sum of
+    /// the rows k of `dense` whose bit k is set in row i, no ring multiplications needed.
+    /// Row blocks are accumulated on separate threads with the `parallel` feature.
+    pub fn mul_dense<R: RingElement + 'static>(&self, dense: &DMatrix<R>) -> DMatrix<R> {
+        assert_eq!(self.ncols, dense.nrows(), "Dimension mismatch");
+        let width = dense.ncols();
+        // the transpose stores the rows of dense contiguously
+        let dense_rows = dense.transpose();
+        let mut out = vec![R::zero(); self.nrows * width];
+
+        for_each_row_block(&mut out, width, |rows, block| {
+            for (i, out_row) in rows.zip(block.chunks_mut(width)) {
+                for (l, limb) in self.row(i).iter().enumerate() {
+                    let mut bits = *limb;
+                    while bits != 0 {
+                        let k = l * 64 + bits.trailing_zeros() as usize;
+                        bits &= bits - 1;
+                        let dense_row = &dense_rows.as_slice()[k * width..(k + 1) * width];
+                        out_row.iter_mut().zip(dense_row).for_each(|(o, x)| *o += *x);
+                    }
+                }
+            }
+        });
+        // out holds the rows of the result, i.e. the column major storage of its transpose
+        DMatrix::from_vec(width, self.nrows, out).transpose()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DMatrix;
+    use num_traits::One;
+
+    use crate::{error_sampling::rnd_dmatrix, field::Fp, gsw::packed::PackedBitMatrix, pow2_ring::Zpow2, RingElement};
+
+    #[test]
+    fn pack_and_multiply() {
+        // widths around the limb size
+        for (nrows, ncols) in [(1, 1), (5, 63), (7, 64), (3, 130)] {
+            let bits: DMatrix<Zpow2<31>> = rnd_dmatrix(nrows, ncols, 0, 1);
+            let packed = PackedBitMatrix::pack(&bits).unwrap();
+            assert_eq!(packed.unpack::<Zpow2<31>>(), bits);
+            assert_eq!(packed.size_in_bytes(), nrows * ncols.div_ceil(64) * 8);
+            assert_eq!(packed.unpack_rows::<Zpow2<31>>(nrows - 1, 1), bits.rows(nrows - 1, 1));
+            let weights = bits.row_iter().map(|row| row.iter().filter(|x| **x == Zpow2::one()).count() as u64);
+            assert_eq!(Some(packed.max_row_weight()), weights.max());
+
+            let dense: DMatrix<Zpow2<31>> = rnd_dmatrix(ncols, 9, 0, Zpow2::<31>::max_u64());
+            assert_eq!(packed.mul_dense(&dense), &bits * &dense);
+        }
+
+        let bits: DMatrix<Fp> = rnd_dmatrix(6, 70, 0, 1);
+        let dense: DMatrix<Fp> = rnd_dmatrix(70, 4, 0, Fp::max_u64());
+        assert_eq!(PackedBitMatrix::pack(&bits).unwrap().mul_dense(&dense), &bits * &dense);
+
+        let not_bits = DMatrix::from_element(2, 2, Zpow2::<31>::from(2));
+        assert_eq!(PackedBitMatrix::pack(&not_bits), None);
+    }
+
+    #[test]
+    #[cfg(feature="use_flatten")]
+    fn packed_ciphertexts() {
+        use num_traits::Zero;
+        use crate::{error_sampling::DiscrGaussianSampler, gsw::{gadget::Gadget, helper::inf_norm, FheScheme, GSW}};
+
+        let fhe = GSW::<Zpow2<31>, DiscrGaussianSampler>::new(4, 4 * 31, DiscrGaussianSampler::default(), Gadget::BINARY).unwrap();
+        let (sk, pk) = fhe.keygen();
+        let one = fhe.encrypt(&pk, Zpow2::one());
+        let zero = fhe.encrypt(&pk, Zpow2::zero());
+        // flattened binary ciphertexts are stored packed
+        let packed = one.packed().unwrap();
+        assert!(one.size_in_bytes() * 30 < one.matrix().len() * size_of::<Zpow2<31>>());
+        assert_eq!(one.inf_norm(), inf_norm(&one.matrix()));
+        assert_eq!(one.rows(3, 2), one.matrix().rows(3, 2));
+
+        // the masked product agrees with the dense one and results stay packed
+        assert_eq!(packed.mul_dense(&zero.matrix()), &*one.matrix() * &*zero.matrix());
+        let nand = fhe.nand(&one, &zero).unwrap();
+        assert!(nand.packed().is_some());
+        assert_eq!(fhe.decrypt(&sk, &nand), Zpow2::one());
+
+        // balanced digits are not bits
+        let fhe = GSW::<Zpow2<31>, DiscrGaussianSampler>::new(4, 4 * 31, DiscrGaussianSampler::default(), Gadget::new(4, true)).unwrap();
+        let (_, pk) = fhe.keygen();
+        assert!(fhe.encrypt(&pk, Zpow2::one()).packed().is_none());
+    }
+}
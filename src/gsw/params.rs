@@ -0,0 +1,177 @@
+use std::marker::PhantomData;
+
+use num_bigint::BigUint;
+use num_rational::Ratio;
+
+use crate::{error::TfheError, error_sampling::{DiscrGaussianSampler, ErrorSampling}, field::P, gsw::{gadget::Gadget, GSW}, RingElement};
+
+
+/// Target security of the LWE instance behind a preset.
+/// The dimensions are rough estimates scaled from the tables of the
+/// HomomorphicEncryption.org standard (uniform secret, stddev 3.2),
+/// i.e. n/log q >= 24 for 80 and n/log q >= 37 for 128 bits.
+/// They have not been checked with the lattice estimator (nor has the binary secret
+/// variant used by the DKG), so the levels are targets, not claims.
+///
+/// The non-toy levels are expensive with the binary gadget: for 31 bit rings
+/// Bits80 has n = 768, N = 23839 and Bits128 has n = 1152, N = 35743,
+/// i.e. dense ciphertexts of about 4.5 GB and 10 GB with 8 byte entries
+/// (about 71 MB and 160 MB when packed, see `PackedBitMatrix`),
+/// and encryption costs about N * m * (n + 1) ring operations.
+/// `GSW::preset_with_gadget` trades noise growth for size with a larger base.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityLevel {
+    /// Tiny dimension for tests and examples, no security at all
+    Toy,
+    Bits80,
+    Bits128,
+}
+
+impl SecurityLevel {
+    /// LWE dimension n for a modulus of `log_q` bits (rounded up to a multiple of 64)
+    pub fn dimension(&self, log_q: usize) -> usize {
+        match self {
+            SecurityLevel::Toy => 4,
+            SecurityLevel::Bits80 => (24 * log_q).div_ceil(64) * 64,
+            SecurityLevel::Bits128 => (37 * log_q).div_ceil(64) * 64,
+        }
+    }
+
+    fn stddev(&self) -> Ratio<BigUint> {
+        match self {
+            SecurityLevel::Toy => Ratio::new(BigUint::from(1u32), BigUint::from(1u32)),
+            _ => Ratio::new(BigUint::from(16u32), BigUint::from(5u32)),
+        }
+    }
+}
+
+impl<R: RingElement + 'static, T: ErrorSampling<R>> GSW<R, T> {
+    /// # Parameters:
+    ///  - `n`: LWE dimension (length of t)
+    ///  - `m`: Number of rows of the public key, at least n * Num_Bits s.t. R*A is close to uniform
+    ///  - `err_sampling`: Distribution of the key error
+    ///  - `gadget`: Gadget used for decomposition, needs at least two digits per ring element
+    ///
+    /// Fails if the parameters are inconsistent, R is wider than the uniform sampler
+    /// used by keygen (see `rnd_ring_elm`) or fresh ciphertexts would not decrypt.
+    pub fn new(n: usize, m: usize, err_sampling: T, gadget: Gadget) -> Result<Self, TfheError> {
+        if n == 0 {
+            return Err(TfheError::InvalidParameters("n must be positive".to_string()));
+        }
+        if gadget.num_digits::<R>() < 2 {
+            return Err(TfheError::InvalidParameters(format!(
+                "gadget base 2^{} leaves a single digit for {} bit ring elements", gadget.log_base(), R::Num_Bits)));
+        }
+        if m < n.saturating_mul(R::Num_Bits) {
+            return Err(TfheError::InvalidParameters(format!(
+                "m = {} is smaller than n * Num_Bits = {}", m, n.saturating_mul(R::Num_Bits))));
+        }
+        if R::max_u64() > P {
+            return Err(TfheError::InvalidParameters(format!(
+                "keygen samples uniform ring elements below P = 2^31 - 1, {} bit ring elements are too wide", R::Num_Bits)));
+        }
+        if err_sampling.error_bound() == 0 {
+            return Err(TfheError::InvalidParameters("error distribution must not be zero".to_string()));
+        }
+
+        let gsw = Self { n, m, err_sampling, gadget, message_modulus: 2, _marker: PhantomData };
+        let (bound, window) = (gsw.fresh_noise_bound(), gsw.noise_window());
+        if bound >= window {
+            return Err(TfheError::NoiseTooLarge { bound, window });
+        }
+        Ok(gsw)
+    }
+
+    /// Sets the message modulus t for messages in [0, t), see `encrypt_message` and `decrypt_message`.
+    /// `encrypt` does not reduce its ring element mod t (see `GSW::fresh_message_bound`).
+    /// Fails if t < 2 or fresh messages already exceed the message window (see `GSW::message_window`).
+    pub fn with_message_modulus(self, t: u64) -> Result<Self, TfheError> {
+        if t < 2 {
+            return Err(TfheError::InvalidParameters("message modulus must be at least 2".to_string()));
+        }
+        let gsw = Self { message_modulus: t, ..self };
+        if t > gsw.message_window() {
+            return Err(TfheError::InvalidParameters(format!(
+                "message modulus {} does not fit into {} bit ring elements", t, R::Num_Bits)));
+        }
+        Ok(gsw)
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    pub fn err_sampling(&self) -> &T {
+        &self.err_sampling
+    }
+
+    pub fn message_modulus(&self) -> u64 {
+        self.message_modulus
+    }
+}
+
+impl<R: RingElement + 'static> GSW<R, DiscrGaussianSampler> {
+    /// Named parameter set for R with the binary gadget and m = n * Num_Bits, see `SecurityLevel` for the cost
+    pub fn preset(level: SecurityLevel) -> Result<Self, TfheError> {
+        Self::preset_with_gadget(level, Gadget::BINARY)
+    }
+
+    /// Same LWE instance as `preset` with another gadget.
+    /// Base 2^k shrinks N by about a factor of k (Bits80 with base 2^8 on 31 bit rings: N = 3076)
+    /// at the cost of noise growing with B instead of 2 per multiplication.
+    pub fn preset_with_gadget(level: SecurityLevel, gadget: Gadget) -> Result<Self, TfheError> {
+        let n = level.dimension(R::Num_Bits);
+        Self::new(n, n * R::Num_Bits, DiscrGaussianSampler::new(level.stddev()), gadget)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use num_traits::{One, Zero};
+
+    use crate::{error::TfheError, error_sampling::{DiscrGaussianSampler, NaiveSampler}, field::Fp, gsw::{gadget::Gadget, params::SecurityLevel, FheScheme, GSW}, pow2_ring::Zpow2, RingElement};
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        let invalid = |res: Result<GSW<Zpow2<31>, DiscrGaussianSampler>, TfheError>| matches!(res, Err(TfheError::InvalidParameters(_)));
+        assert!(invalid(GSW::new(0, 31, DiscrGaussianSampler::default(), Gadget::BINARY)));
+        assert!(invalid(GSW::new(4, 4 * 31 - 1, DiscrGaussianSampler::default(), Gadget::BINARY)));
+        assert!(invalid(GSW::new(4, 4 * 31, DiscrGaussianSampler::default(), Gadget::new(31, false))));
+        assert!(GSW::<Zpow2<31>, _>::new(4, 4 * 31, DiscrGaussianSampler::default(), Gadget::new(16, true)).is_ok());
+        // keygen could not sample uniform elements of wider rings
+        assert!(matches!(GSW::<Zpow2<32>, _>::new(4, 4 * 32, DiscrGaussianSampler::default(), Gadget::BINARY), Err(TfheError::InvalidParameters(_))));
+
+        // 400 * 31 * 65535 > 2^31 / 4
+        assert!(matches!(GSW::<Fp, _>::new(4, 400 * 31, NaiveSampler, Gadget::BINARY), Err(TfheError::NoiseTooLarge { .. })));
+
+        let fhe = || GSW::<Fp, _>::new(4, 4 * 31, DiscrGaussianSampler::default(), Gadget::BINARY).unwrap();
+        assert!(matches!(fhe().with_message_modulus(1), Err(TfheError::InvalidParameters(_))));
+        assert!(matches!(fhe().with_message_modulus(1 << 31), Err(TfheError::InvalidParameters(_))));
+        assert_eq!(fhe().with_message_modulus(10).unwrap().message_modulus(), 10);
+    }
+
+    #[test]
+    fn presets() {
+        for level in [SecurityLevel::Toy, SecurityLevel::Bits80, SecurityLevel::Bits128] {
+            let fp = GSW::<Fp, DiscrGaussianSampler>::preset(level).unwrap();
+            let pow2 = GSW::<Zpow2<31>, DiscrGaussianSampler>::preset(level).unwrap();
+            assert_eq!(fp.n(), level.dimension(Fp::Num_Bits));
+            assert_eq!(pow2.m(), pow2.n() * 31);
+        }
+        assert_eq!(SecurityLevel::Bits80.dimension(31), 768);
+        assert_eq!(SecurityLevel::Bits128.dimension(31), 1152);
+        assert_eq!(GSW::<Zpow2<31>, DiscrGaussianSampler>::preset(SecurityLevel::Bits80).unwrap().ciphertext_dim(), 23839);
+        let wide_base = GSW::<Zpow2<31>, DiscrGaussianSampler>::preset_with_gadget(SecurityLevel::Bits80, Gadget::new(8, false)).unwrap();
+        assert_eq!(wide_base.ciphertext_dim(), 3076);
+
+        let fhe = GSW::<Fp, DiscrGaussianSampler>::preset(SecurityLevel::Toy).unwrap();
+        let (sk, pk) = fhe.keygen();
+        assert_eq!(fhe.decrypt(&sk, &fhe.encrypt(&pk, Fp::one())), Fp::one());
+        assert_eq!(fhe.decrypt(&sk, &fhe.encrypt(&pk, Fp::zero())), Fp::zero());
+    }
+}
@@ -1,18 +1,34 @@
 use nalgebra::{DMatrix, DVector};
 
-use crate::{field::Fp, RingElement};
+use crate::{error::TfheError, error_sampling::{seeded_dmatrix, ErrorSampling}, field::Fp, gsw::{ciphertext::stable_hash, GSW}, RingElement};
 
 
+/// Seed of the PRG (ChaCha20) the random matrix B is expanded from
+pub type Seed = [u8; 32];
+
 /// Contains all components used in generating the public key.
 /// (B,e as well for testing purposes!!)
 /// b = Bt+e
 /// pk = b||B
+/// seed: set if B was derived from it, the key can then be shipped as (seed, b)
+/// error_bound: bound on |e|_inf, determines the noise of encryptions under the key
 /// 
 #[derive(PartialEq)]
 #[derive(Debug)]
 pub struct GswPk<R: RingElement> {
     pub b: DVector<R>,
-    pub pk_matrix: DMatrix<R>
+    pub pk_matrix: DMatrix<R>,
+    seed: Option<Seed>,
+    fingerprint: u64,
+    error_bound: u64,
+}
+
+/// Public key (seed, b) without the m x n matrix B, see `GSW::decompress_pk`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedGswPk<R: RingElement> {
+    pub seed: Seed,
+    pub b: DVector<R>,
+    pub error_bound: u64,
 }
 
 
@@ -24,6 +40,18 @@ impl<R: RingElement + 'static> GswPk<R> {
         assert_eq!(random_matrix.ncols(), t.len(), "Dimension Mismatch ncols {}, {}", random_matrix.ncols(), t.len());
         assert_eq!(random_matrix.nrows(), e.len(), "Dimension Mismatch nrows {}, {}", random_matrix.nrows(), e.len());
         let b: DVector<R> = random_matrix * t + e;
+        Self::from_parts(b, random_matrix)
+    }
+
+    /// Same as `new` with B expanded from the seed
+    pub fn from_seed(seed: Seed, e: &DVector<R>, t: &DVector<R>) -> Self {
+        let random_matrix = seeded_dmatrix(&seed, e.len(), t.len(), 0, R::max_u64());
+        Self { seed: Some(seed), ..Self::new(&random_matrix, e, t) }
+    }
+
+    /// Assembles pk = b||B from an already computed b (e.g. aggregated in a DKG)
+    pub fn from_parts(b: DVector<R>, random_matrix: &DMatrix<R>) -> Self {
+        assert_eq!(random_matrix.nrows(), b.len(), "Dimension Mismatch nrows {}, {}", random_matrix.nrows(), b.len());
 
         let mut pk_matrix = DMatrix::zeros(random_matrix.nrows(), random_matrix.ncols() + 1);
 
@@ -35,7 +63,51 @@ impl<R: RingElement + 'static> GswPk<R> {
             .view_mut((0, 1), (random_matrix.nrows(), random_matrix.ncols()))
             .copy_from(random_matrix);
 
-        Self { b, pk_matrix }
+        let fingerprint = stable_hash(pk_matrix.iter().flat_map(|x| Into::<u64>::into(*x).to_le_bytes()));
+        Self { b, pk_matrix, seed: None, fingerprint, error_bound: u64::MAX }
+    }
+
+    /// Sets the bound on |e|_inf. Keys are assembled with the unknown bound u64::MAX,
+    /// so encryptions under them cannot be decrypted with a noise check unless the bound is set.
+    pub fn with_error_bound(self, error_bound: u64) -> Self {
+        Self { error_bound, ..self }
+    }
+
+    /// Same as `from_parts` with the b.len() x ncols matrix B expanded from the seed
+    pub fn from_seed_parts(seed: Seed, b: DVector<R>, ncols: usize) -> Self {
+        let random_matrix = seeded_dmatrix(&seed, b.len(), ncols, 0, R::max_u64());
+        Self { seed: Some(seed), ..Self::from_parts(b, &random_matrix) }
+    }
+
+    /// Hash of pk_matrix, identifies the key ciphertexts are encrypted under
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    pub fn error_bound(&self) -> u64 {
+        self.error_bound
+    }
+
+    pub fn seed(&self) -> Option<&Seed> {
+        self.seed.as_ref()
+    }
+
+    /// (seed, b), `None` if B was not derived from a seed
+    pub fn compress(&self) -> Option<CompressedGswPk<R>> {
+        Some(CompressedGswPk { seed: self.seed?, b: self.b.clone(), error_bound: self.error_bound })
+    }
+}
+
+impl<R: RingElement + 'static, T: ErrorSampling<R>> GSW<R, T> {
+    /// Regenerates B from the seed. The result is cached in `pk_matrix`,
+    /// so B is expanded once per key rather than once per encryption.
+    /// Fails if b does not have m entries.
+    pub fn decompress_pk(&self, compressed: &CompressedGswPk<R>) -> Result<GswPk<R>, TfheError> {
+        if compressed.b.len() != self.m {
+            return Err(TfheError::InvalidParameters(format!(
+                "public key has {} rows, expected m = {}", compressed.b.len(), self.m)));
+        }
+        Ok(GswPk::from_seed_parts(compressed.seed, compressed.b.clone(), self.n).with_error_bound(compressed.error_bound))
     }
 }
 
@@ -44,7 +116,9 @@ impl<R: RingElement + 'static> GswPk<R> {
 mod tests {
     use nalgebra::DMatrix;
 
-    use crate::{error_sampling::{rnd_dmatrix, rnd_dvec}, field::{Fp, P}, gsw::pk::GswPk};
+    use num_traits::{One, Zero};
+
+    use crate::{error::TfheError, error_sampling::{rnd_dmatrix, rnd_dvec, DiscrGaussianSampler}, field::{Fp, P}, gsw::{gadget::Gadget, pk::{CompressedGswPk, GswPk}, FheScheme, GSW}, pow2_ring::Zpow2, RingElement};
 
     #[test]
     fn different_err_lead_to_diff_pk() {  
@@ -62,4 +136,28 @@ mod tests {
 
         assert_ne!(pk1, pk2);
     }
+
+    #[test]
+    fn compressed_public_key() {
+        let fhe = GSW::<Zpow2<31>, DiscrGaussianSampler>::new(4, 4 * 31, DiscrGaussianSampler::default(), Gadget::BINARY).unwrap();
+        let (sk, pk) = fhe.keygen();
+        let compressed = pk.compress().unwrap();
+        // n + 1 times smaller than pk_matrix (plus the seed)
+        assert_eq!(compressed.b.len() * (fhe.n() + 1), pk.pk_matrix.len());
+
+        let decompressed = fhe.decompress_pk(&compressed).unwrap();
+        assert_eq!(decompressed, pk);
+        let ct = fhe.encrypt(&decompressed, Zpow2::one());
+        assert_eq!(ct.fingerprint().key, Some(pk.fingerprint()));
+        assert_eq!(fhe.decrypt(&sk, &ct), Zpow2::one());
+        assert_eq!(fhe.decrypt(&sk, &fhe.encrypt(&decompressed, Zpow2::zero())), Zpow2::zero());
+
+        assert_eq!(fhe.pk_noise_bound(&decompressed), fhe.fresh_noise_bound());
+        let truncated = CompressedGswPk { b: compressed.b.rows(0, 10).into_owned(), ..compressed };
+        assert!(matches!(fhe.decompress_pk(&truncated), Err(TfheError::InvalidParameters(_))));
+
+        // keys assembled from an explicit B cannot be compressed
+        let random_matrix = rnd_dmatrix(fhe.m(), fhe.n(), 0, Zpow2::<31>::max_u64());
+        assert_eq!(GswPk::new(&random_matrix, &rnd_dvec(fhe.m(), 0, 0), sk.t()).compress(), None);
+    }
 }
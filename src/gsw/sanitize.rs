@@ -0,0 +1,124 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::{error::TfheError, error_sampling::{rnd_dmatrix, ErrorSampling}, gsw::{ciphertext::GswCiphertext, helper::{bit_decomp_inv_matrix, bit_decomp_matrix}, matmul::mat_mul, pk::GswPk, GSW}, RingElement};
+
+
+/// Circuit privacy: evaluated ciphertexts depend on the circuit that produced them
+/// (entries of products, noise). Both procedures are meant to be applied before a
+/// ciphertext is handed to decryption parties.
+impl<R: RingElement + 'static, T: ErrorSampling<R>> GSW<R, T> {
+    /// BitDecomp(BitDecomp^-1(C) + R*pk): re-randomizes the ciphertext at the cost of fresh noise.
+    /// The result has the shape of a fresh ciphertext (BitDecomp of a one-time padded matrix)
+    /// independently of `use_flatten`, adding Enc(0) to C directly would leave C visible
+    /// in the unflattened sum. Hides which fresh encryptions were used, but not the noise of C.
+    pub fn rerandomize(&self, pk: &GswPk<R>, ciphertext: &GswCiphertext<R>) -> Result<GswCiphertext<R>, TfheError> {
+        let fingerprint = self.check_pk(pk, ciphertext)?;
+        let noise_bound = ciphertext.noise_bound().saturating_add(self.pk_noise_bound(pk));
+        let res = self.pad_with_zero_encryption(pk, ciphertext, None);
+        Ok(GswCiphertext::new(self.store_flattened(res), fingerprint, true, noise_bound, ciphertext.message_bound()))
+    }
+
+    /// Turns C with C*v = mu*v + e into BitDecomp(BitDecomp^-1(C) + Y),
+    /// where Y = R*pk + (f | 0) is a fresh encryption of zero with flooded error f.
+    /// BitDecomp^-1(C) is one-time padded by Y and e is hidden by f: if the flooding bound is
+    /// at least 2^lambda * noise_bound(C), the result is within statistical distance about
+    /// N * 2^-lambda of sanitizing a fresh encryption of mu.
+    /// The noise of Y is bounded with the key's error bound (see `pk_noise_bound`).
+    /// Fails with `SmudgingTooLarge` if the ring is too small for lambda (2^lambda * noise_bound(C)
+    /// alone reaches the decoding window), with `InvalidParameters` if the flooding bound is below it,
+    /// with `NoiseTooLarge` if the flooded noise exceeds the decoding window
+    /// and with `IncompatibleCiphertexts` if the key does not match.
+    pub fn sanitize<F: ErrorSampling<R>>(&self, pk: &GswPk<R>, ciphertext: &GswCiphertext<R>, flooding: &F, lambda: u32) -> Result<GswCiphertext<R>, TfheError> {
+        let fingerprint = self.check_pk(pk, ciphertext)?;
+
+        let window = self.noise_window();
+        let required = u64::try_from((ciphertext.noise_bound() as u128) << lambda.min(64)).unwrap_or(u64::MAX);
+        if required >= window {
+            return Err(TfheError::SmudgingTooLarge { total: required, window });
+        }
+        if flooding.error_bound() < required {
+            return Err(TfheError::InvalidParameters(format!(
+                "flooding bound {} is below 2^{} times the noise bound {}", flooding.error_bound(), lambda, ciphertext.noise_bound())));
+        }
+
+        let noise_bound = ciphertext.noise_bound()
+            .saturating_add(self.pk_noise_bound(pk))
+            .saturating_add(flooding.error_bound());
+        if noise_bound >= window {
+            return Err(TfheError::NoiseTooLarge { bound: noise_bound, window });
+        }
+
+        let flood = flooding.rnd_error_dvec(self.ciphertext_dim());
+        let res = self.pad_with_zero_encryption(pk, ciphertext, Some(flood));
+        Ok(GswCiphertext::new(self.store_flattened(res), fingerprint, true, noise_bound, ciphertext.message_bound()))
+    }
+
+    /// BitDecomp(BitDecomp^-1(C) + Y) for Y = R*pk + (f | 0), where f is added to the first column
+    fn pad_with_zero_encryption(&self, pk: &GswPk<R>, ciphertext: &GswCiphertext<R>, flood: Option<DVector<R>>) -> DMatrix<R> {
+        let random_matrix: DMatrix<R> = rnd_dmatrix(self.ciphertext_dim(), self.m, 0, 1);
+        let mut padded = mat_mul(&random_matrix, &pk.pk_matrix);
+        // s = (1, -t), so adding f to the first column adds f to padded*s
+        if let Some(flood) = flood {
+            padded.column_mut(0).zip_apply(&flood, |x, f| *x += f);
+        }
+
+        let mut composed = ciphertext.matrix().into_owned();
+        bit_decomp_inv_matrix(&mut composed, &self.gadget);
+        let mut res = composed + padded;
+        bit_decomp_matrix(&mut res, &self.gadget);
+        res
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use num_traits::{One, Zero};
+
+    use crate::{error::TfheError, error_sampling::{DiscrGaussianSampler, ErrorSampling, UniformSampler}, gsw::{gadget::Gadget, helper::centered_abs, FheScheme, GSW}, pow2_ring::Zpow2};
+
+    #[test]
+    fn rerandomize_and_sanitize() {
+        let fhe = GSW::<Zpow2<31>, DiscrGaussianSampler>::new(4, 4 * 31, DiscrGaussianSampler::default(), Gadget::BINARY).unwrap();
+        let (sk, pk) = fhe.keygen();
+        let one = fhe.encrypt(&pk, Zpow2::one());
+        let evaluated = fhe.nand(&one, &one).unwrap();
+        let noise = |ct: &_| centered_abs(fhe.decryption_row(ct).dot(sk.v()));
+
+        // the result is the BitDecomp of a padded matrix, i.e. a bit matrix like a fresh ciphertext
+        let rerandomized = fhe.rerandomize(&pk, &evaluated).unwrap();
+        assert_ne!(rerandomized.matrix(), evaluated.matrix());
+        assert!(rerandomized.is_flattened());
+        assert!(rerandomized.matrix().iter().all(|x| *x == Zpow2::zero() || *x == Zpow2::one()));
+        assert_eq!(rerandomized.matrix().shape(), (fhe.ciphertext_dim(), fhe.ciphertext_dim()));
+        assert!(noise(&rerandomized) <= rerandomized.noise_bound());
+        assert_eq!(fhe.checked_decrypt(&sk, &rerandomized), Ok(Zpow2::zero()));
+        assert_eq!(fhe.checked_decrypt(&sk, &fhe.rerandomize(&pk, &one).unwrap()), Ok(Zpow2::one()));
+
+        let flooding = UniformSampler::new(1 << 26);
+        let sanitized = fhe.sanitize(&pk, &evaluated, &flooding, 8).unwrap();
+        assert_eq!(fhe.checked_decrypt(&sk, &sanitized), Ok(Zpow2::zero()));
+        assert!(sanitized.is_flattened());
+        assert!(sanitized.matrix().iter().all(|x| *x == Zpow2::zero() || *x == Zpow2::one()));
+        assert!(noise(&sanitized) <= sanitized.noise_bound());
+        assert!(sanitized.noise_bound() > ErrorSampling::<Zpow2<31>>::error_bound(&flooding));
+
+        // sanitized ciphertexts of 1 still work with all operations
+        let sanitized_one = fhe.sanitize(&pk, &one, &flooding, 8).unwrap();
+        assert_eq!(fhe.checked_decrypt(&sk, &sanitized_one), Ok(Zpow2::one()));
+        assert_eq!(fhe.mp_decrypt(&sk, &sanitized_one), Zpow2::one());
+        assert_eq!(fhe.decrypt(&sk, &fhe.nand(&sanitized_one, &one).unwrap()), Zpow2::zero());
+
+        assert!(matches!(fhe.sanitize(&pk, &evaluated, &UniformSampler::new(1 << 29), 8), Err(TfheError::NoiseTooLarge { .. })));
+        let (_, other_pk) = fhe.keygen();
+        assert_eq!(fhe.sanitize(&other_pk, &evaluated, &flooding, 8), Err(TfheError::IncompatibleCiphertexts));
+
+        // the flooding has to exceed 2^lambda times the noise, which 31 bit rings cannot do for lambda = 40
+        assert!(matches!(fhe.sanitize(&pk, &evaluated, &flooding, 40), Err(TfheError::SmudgingTooLarge { .. })));
+        assert!(matches!(fhe.sanitize(&pk, &evaluated, &UniformSampler::new(1 << 20), 8), Err(TfheError::InvalidParameters(_))));
+
+        // the noise of the padding is bounded with the key's error bound
+        let loose_pk = pk.with_error_bound(1 << 22);
+        assert!(matches!(fhe.sanitize(&loose_pk, &evaluated, &flooding, 8), Err(TfheError::NoiseTooLarge { .. })));
+    }
+}
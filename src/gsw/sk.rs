@@ -3,28 +3,49 @@ use nalgebra::DVector;
 
 use crate::field::{Fp};
 use crate::gsw::helper::powers_of_2;
+use crate::gsw::gadget::Gadget;
 use crate::gsw::{build_gadget_vector, RingElement};
+use crate::secret::Secret;
 
 
 /// Contains all components of a private key. 
 /// t n-dim random vector \in \mathbb(Z)_p
 /// s (1, -t_1, -t_2,...) \in \mathbb(Z)_p
-/// v = powers_of_two(s)
-pub struct GswSk<R: RingElement> {
-    pub t: DVector<R>,
-    pub s: DVector<R>,
-    pub v: DVector<R>,
+/// v = powers_of_two(s) w.r.t. the gadget (1, B, ..., B^{l-1})
+/// key_fingerprint: fingerprint of the matching pk if known (set by keygen)
+/// t, s and v are wiped on drop and redacted in `Debug` output.
+#[derive(Debug)]
+pub struct GswSk<R: RingElement + 'static> {
+    t: Secret<DVector<R>>,
+    s: Secret<DVector<R>>,
+    v: Secret<DVector<R>>,
+    pub key_fingerprint: Option<u64>,
 }
 
 impl<R: RingElement + 'static> GswSk<R> {
-    pub fn new(t: DVector<R>) -> Self {
-        let mut s: nalgebra::Matrix<R, nalgebra::Dyn, nalgebra::Const<1>, nalgebra::VecStorage<R, nalgebra::Dyn, nalgebra::Const<1>>> = DVector::zeros(t.len() + 1);
-        s[0] = R::one();
+    pub fn new(t: DVector<R>, gadget: &Gadget) -> Self {
+        let t = Secret::new(t);
+        let mut s = Secret::new(DVector::zeros(t.expose().len() + 1));
+        s.expose_mut()[0] = R::one();
+        // negate in place, -t would be an unwiped temporary
+        for (s_i, t_i) in s.expose_mut().iter_mut().skip(1).zip(t.expose().iter()) {
+            *s_i = -*t_i;
+        }
+
+        let v = Secret::new(powers_of_2(s.expose(), &build_gadget_vector(gadget)));
+        Self { t, s, v, key_fingerprint: None }
+    }
 
-        s.rows_mut(1, t.len()).copy_from(&(-t.clone()));
+    pub fn t(&self) -> &DVector<R> {
+        self.t.expose()
+    }
+
+    pub fn s(&self) -> &DVector<R> {
+        self.s.expose()
+    }
 
-        let v = powers_of_2(&s, &build_gadget_vector());
-        Self { t, s, v }
+    pub fn v(&self) -> &DVector<R> {
+        self.v.expose()
     }
 }
 
@@ -32,14 +53,26 @@ impl<R: RingElement + 'static> GswSk<R> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{error_sampling::rnd_dvec, field::Fp, gsw::sk::GswSk, RingElement};
+    use crate::{error_sampling::rnd_dvec, field::Fp, gsw::{gadget::Gadget, sk::GswSk}, RingElement};
 
     #[test]
     fn test_v_decomp() {
-        let sk: GswSk<Fp> = GswSk::new(rnd_dvec(5, 0, 10));
+        let sk: GswSk<Fp> = GswSk::new(rnd_dvec(5, 0, 10), &Gadget::BINARY);
 
         for i in 0..Fp::Num_Bits {
-            assert_eq!(sk.v[i], Fp::from(1<<i));
+            assert_eq!(sk.v()[i], Fp::from(1<<i));
+        }
+    }
+
+    #[test]
+    fn test_v_decomp_base_16() {
+        let t = rnd_dvec(5, 0, 10);
+        let sk: GswSk<Fp> = GswSk::new(t.clone(), &Gadget::new(4, false));
+        assert_eq!(sk.v().len(), 6 * 8);
+
+        for i in 0..8 {
+            assert_eq!(sk.v()[i], Fp::from(1<<(4 * i)));
+            assert_eq!(sk.v()[8 + i], -t[0] * Fp::from(1<<(4 * i)));
         }
     }
 }
\ No newline at end of file
@@ -3,23 +3,27 @@ use std::{iter::Sum, marker::PhantomData, ops::{Add, AddAssign, Mul, MulAssign,
 use ff::{derive::bitvec::array::BitArray};
 use num_traits::Bounded;
 
-use crate::{field::Fp, gsw::{FheScheme}, zo_sss::{Party, SecretSharingScheme}};
+use crate::{error::TfheError, error_sampling::ErrorSampling, gsw::{FheScheme}, zo_sss::SecretSharingScheme};
 
+pub mod dkg;
+pub mod error;
 pub mod field;
 pub mod gsw;
 pub mod zo_sss;
 pub mod error_sampling;
 pub mod pow2_ring;
-pub mod tfhe_gsw_fp;
+pub mod secret;
+pub mod tfhe_gsw;
+pub mod thresholdizer;
 
 pub trait RingElement:
-    Clone + Copy + PartialEq + Eq + std::fmt::Debug 
+    Clone + Copy + PartialEq + Eq + std::fmt::Debug + Send + Sync
     + Add<Output = Self> + Sub<Output = Self>
     + Mul<Output = Self> + Neg<Output = Self>
     + AddAssign + SubAssign + MulAssign
     + num_traits::Zero + num_traits::One
     + From<u64> + Ord + Bounded
-    + Into<u64>
+    + Into<u64> + zeroize::DefaultIsZeroes
 where
     for<'a> Self: Sum<&'a Self>,
 {
@@ -31,32 +35,84 @@ where
 
 /// The TFHE scheme is fully described by
 /// the Ring over which it operates,
-/// the secret sharing scheme and 
-/// the FHE scheme it uses
-pub struct TfheStructure<R, S, E>
+/// the secret sharing scheme, 
+/// the FHE scheme it uses and
+/// the noise distribution used to flood partial decryptions
+pub struct TfheStructure<R, S, E, F>
 where
     R: RingElement,
     S: SecretSharingScheme<R>,
     E: FheScheme<R>,
+    F: ErrorSampling<R>,
 {
     pub secret_sharing_scheme: S,
     pub fhe_scheme: E,
+    pub smudging: F,
     _marker: PhantomData<R>
 }
 
+impl<R, S, E, F> TfheStructure<R, S, E, F>
+where
+    R: RingElement,
+    S: SecretSharingScheme<R>,
+    E: FheScheme<R>,
+    F: ErrorSampling<R>,
+{
+    pub fn new(secret_sharing_scheme: S, fhe_scheme: E, smudging: F) -> Self {
+        Self { secret_sharing_scheme, fhe_scheme, smudging, _marker: PhantomData }
+    }
+}
+
+/// Output of a single party in the partial decryption step.
+/// `shares` holds one (flooded) value per key share the party owns (same order as `share_ids`)
+/// `name` identifies the party so that a combiner knows which shares were used.
+/// `noise_bound` is the noise bound of the decrypted ciphertext (without flooding).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialDecryption<R: RingElement> {
+    pub name: u8,
+    pub share_ids: Vec<u8>,
+    pub shares: Vec<R>,
+    pub noise_bound: u64,
+}
+
+/// Output of a single party in the multi-bit partial decryption step.
+/// shares[k][j] is the (flooded) contribution of the j-th key share to gadget row k
+/// (same layout as `Party::shares`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MpPartialDecryption<R: RingElement> {
+    pub name: u8,
+    pub share_ids: Vec<u8>,
+    pub shares: Vec<Vec<R>>,
+    pub noise_bound: u64,
+}
+
+/// Result of a robust final decryption.
+/// `inconsistent` lists the parties that are not contained in any minimal
+/// qualified set decoding to `value`.
+/// `checked_sets` is the number of minimal qualified sets that were cross-checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RobustDecryption<R: RingElement> {
+    pub value: R,
+    pub inconsistent: Vec<u8>,
+    pub checked_sets: usize,
+}
+
 /// Implements all TFHE funktionality as described by Boneh et al
 pub trait TfheScheme<R: RingElement> {
     type SecretKey;
     type PublicKey;
     type Ciphertext;
+    type KeyShare;
 
-    fn setup(&self) -> (Vec<Party<R>>, Self::PublicKey);
-    fn encrypt(&self, pk: &Self::PublicKey, message: Fp) -> Self::Ciphertext;
-    fn part_dec(&self, pk: &Self::PublicKey, ciphertext: Self::Ciphertext, party: Party<R>);
-    // fn fin_dec();
+    fn setup(&self) -> (Vec<Self::KeyShare>, Self::PublicKey);
+    fn encrypt(&self, pk: &Self::PublicKey, message: R) -> Self::Ciphertext;
+    fn part_dec(&self, pk: &Self::PublicKey, ciphertext: &Self::Ciphertext, key_share: &Self::KeyShare) -> Result<PartialDecryption<R>, TfheError>;
+    fn fin_dec(&self, partial_decryptions: &[PartialDecryption<R>]) -> Result<R, TfheError>;
+    fn mp_part_dec(&self, pk: &Self::PublicKey, ciphertext: &Self::Ciphertext, key_share: &Self::KeyShare) -> Result<MpPartialDecryption<R>, TfheError>;
+    fn mp_fin_dec(&self, partial_decryptions: &[MpPartialDecryption<R>]) -> Result<R, TfheError>;
     
-    fn add(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Self::Ciphertext;
-    fn mult_const(&self, ciphertext: &mut Self::Ciphertext, constant: Fp);
-    fn mult(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Self::Ciphertext;
-    fn nand(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Self::Ciphertext;
+    fn add(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError>;
+    fn mult_const(&self, ciphertext: &mut Self::Ciphertext, constant: R) -> Result<(), TfheError>;
+    fn mult(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError>;
+    fn nand(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError>;
 }
@@ -10,7 +10,7 @@ use crate::RingElement;
 
 /// Integer ring Z_{2^M} (M is exponent)
 /// Only supports 1 <= M <= 64 so everything fits in `u64`.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Zpow2<const M: u64> {
     value: u64,
 }
@@ -33,6 +33,9 @@ impl<const M: u64> RingElement for Zpow2<M> {
     }
 }
 
+/// Default is zero, so secrets in Z_{2^M} can be wiped
+impl<const M: u64> zeroize::DefaultIsZeroes for Zpow2<M> {}
+
 impl<const M: u64> Zpow2<M> {
     /// Compute mask = 2^M - 1 as u64. Valid only for 1..=64.
     #[inline]
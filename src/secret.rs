@@ -0,0 +1,97 @@
+use std::fmt;
+
+use nalgebra::DVector;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::RingElement;
+
+
+/// Containers of secret ring elements that can be wiped.
+/// The writes are done by the `zeroize` crate. nalgebra types do not implement
+/// `Zeroize`, so they are wiped through their slices.
+pub trait Wipe {
+    fn wipe(&mut self);
+}
+
+impl<R: RingElement + 'static> Wipe for DVector<R> {
+    fn wipe(&mut self) {
+        self.as_mut_slice().zeroize();
+    }
+}
+
+impl<R: RingElement + 'static> Wipe for Vec<DVector<R>> {
+    fn wipe(&mut self) {
+        self.iter_mut().for_each(Wipe::wipe);
+    }
+}
+
+/// Also wipes the spare capacity and clears the vectors
+impl<R: RingElement> Wipe for Vec<Vec<R>> {
+    fn wipe(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// Holds secret key material: wiped on drop, redacted in `Debug` output
+/// and only readable through `expose`.
+/// There is no `PartialEq`, compare the exposed values where needed (tests).
+/// Copies made before wrapping (or by reallocating the inner value) are not wiped.
+#[derive(Clone)]
+pub struct Secret<T: Wipe>(T);
+
+impl<T: Wipe> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    pub fn expose_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Wipe> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.wipe();
+    }
+}
+
+impl<T: Wipe> ZeroizeOnDrop for Secret<T> {}
+
+impl<T: Wipe> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DVector;
+    use num_traits::Zero;
+
+    use crate::{error_sampling::rnd_dvec, gsw::{gadget::Gadget, sk::GswSk}, pow2_ring::Zpow2, secret::{Secret, Wipe}, zo_sss::{dimacs::{DIMACS, DIMACS_2_OF_3_SCHEME}, SecretSharingScheme, MBF}};
+
+    #[test]
+    fn wipe_and_redact() {
+        let mut values: Vec<Vec<Zpow2<31>>> = vec![vec![Zpow2::from(7), Zpow2::from(8)], vec![Zpow2::from(9)]];
+        values.wipe();
+        assert!(values.is_empty());
+
+        let mut secret = Secret::new(DVector::from_element(3, Zpow2::<31>::from(12345)));
+        assert_eq!(format!("{:?}", secret), "Secret([REDACTED])");
+        secret.expose_mut().wipe();
+        assert!(secret.expose().iter().all(|x| x.is_zero()));
+
+        let sk: GswSk<Zpow2<31>> = GswSk::new(DVector::from_element(2, Zpow2::from(12345)), &Gadget::BINARY);
+        assert!(!format!("{:?}", sk).contains("12345"));
+        assert_eq!(sk.t()[0], Zpow2::from(12345));
+
+        let sss = MBF::new(DIMACS::parse(DIMACS_2_OF_3_SCHEME));
+        let parties = sss.share(rnd_dvec::<Zpow2<31>>(2, 0, 1000).iter().copied().collect());
+        assert!(format!("{:?}", parties[0]).contains("REDACTED"));
+    }
+}
@@ -0,0 +1,666 @@
+use std::collections::HashSet;
+
+use crate::{error::TfheError, error_sampling::ErrorSampling, gsw::{gsw_impl::{recover_message, round_to_bit}, key_share::KeyShare, FheScheme, GSW}, zo_sss::{Party, SecretSharingScheme}, MpPartialDecryption, PartialDecryption, RingElement, RobustDecryption, TfheScheme, TfheStructure};
+
+
+impl<R, S, T, F> TfheStructure<R, S, GSW<R, T>, F>
+where
+    R: RingElement + 'static,
+    S: SecretSharingScheme<R>,
+    T: ErrorSampling<R>,
+    F: ErrorSampling<R>,
+{
+    /// fin_dec sums one flooded value per share, so the decoded value carries
+    /// `noise_bound + num_shares * smudging bound` noise. This has to stay below
+    /// the decoding windows of `decrypt` and `mp_decrypt` (see `GSW::noise_window` and `GSW::mp_noise_window`).
+    /// `part_dec` and `mp_part_dec` enforce their window for every ciphertext.
+    /// # Parameters:
+    ///  - `noise_bound`: Noise bound of the ciphertexts to decrypt (e.g. `GSW::fresh_noise_bound`)
+    pub fn check_smudging(&self, noise_bound: u64) -> Result<(), TfheError> {
+        let total = self.flooded_noise_bound(noise_bound);
+        let window = std::cmp::min(self.fhe_scheme.noise_window(), self.fhe_scheme.mp_noise_window());
+        if total < window {
+            Ok(())
+        } else {
+            Err(TfheError::SmudgingTooLarge { total, window })
+        }
+    }
+
+    /// Bound on the noise of a combined partial decryption:
+    /// the ciphertext noise plus one flooding sample per share
+    fn flooded_noise_bound(&self, noise_bound: u64) -> u64 {
+        let flooding = self.secret_sharing_scheme.num_shares() as u128 * self.smudging.error_bound() as u128;
+        u64::try_from(flooding + noise_bound as u128).unwrap_or(u64::MAX)
+    }
+
+    /// Fails if a combined partial decryption of a ciphertext with the given noise bound can leave `window`:
+    /// with `SmudgingTooLarge` if the flooding noise alone does, with `NoiseTooLarge` otherwise
+    fn check_flooded_noise(&self, noise_bound: u64, window: u64) -> Result<(), TfheError> {
+        let flooding = self.flooded_noise_bound(0);
+        if flooding >= window {
+            return Err(TfheError::SmudgingTooLarge { total: flooding, window });
+        }
+        let bound = self.flooded_noise_bound(noise_bound);
+        if bound < window {
+            Ok(())
+        } else {
+            Err(TfheError::NoiseTooLarge { bound, window })
+        }
+    }
+
+    /// Proactively refreshes the key shares of all parties (run locally), see `KeyShare::refresh`.
+    /// pk and existing ciphertexts stay valid since the shared v is unchanged.
+    pub fn refresh_key_shares(&self, key_shares: &mut [KeyShare<R>]) {
+        let deals: Vec<Vec<Party<R>>> = key_shares.iter()
+            .map(|_| KeyShare::refresh_deal(self.fhe_scheme.n + 1, &self.secret_sharing_scheme))
+            .collect();
+
+        for key_share in key_shares.iter_mut() {
+            let received: Vec<Party<R>> = deals.iter()
+                .flat_map(|d| d.iter().filter(|p| p.name == key_share.name).cloned())
+                .collect();
+            key_share.refresh(&received, self.fhe_scheme.gadget());
+        }
+    }
+
+    /// Like `fin_dec` but decodes every minimal qualified subset of the given parties
+    /// and cross-checks the results, s.t. a faulty partial decryption is detected
+    /// (and its party reported) instead of silently producing a wrong output.
+    /// Malformed partial decryptions (see `is_well_formed`) and subsets that fail to decode
+    /// count against their parties instead of aborting the decryption.
+    /// Redundancy is only available if the parties contain more than one minimal qualified set.
+    pub fn robust_fin_dec(&self, partial_decryptions: &[PartialDecryption<R>]) -> Result<RobustDecryption<R>, TfheError> {
+        let window = self.fhe_scheme.noise_window();
+        let names = partial_decryptions.iter()
+            .map(|d| (d.name, self.is_well_formed(&d.share_ids, std::iter::once(d.shares.len()), 1, d.noise_bound, window)));
+        self.robust_combine(names, |set| {
+            let subset: Vec<PartialDecryption<R>> = partial_decryptions.iter()
+                .filter(|d| set.contains(&d.name))
+                .cloned()
+                .collect();
+            self.fin_dec(&subset)
+        })
+    }
+
+    /// Robust version of `mp_fin_dec`, see `robust_fin_dec`
+    pub fn robust_mp_fin_dec(&self, partial_decryptions: &[MpPartialDecryption<R>]) -> Result<RobustDecryption<R>, TfheError> {
+        let (num_rows, window) = (self.fhe_scheme.gadget().num_digits::<R>(), self.fhe_scheme.mp_noise_window());
+        let names = partial_decryptions.iter()
+            .map(|d| (d.name, self.is_well_formed(&d.share_ids, d.shares.iter().map(Vec::len), num_rows, d.noise_bound, window)));
+        self.robust_combine(names, |set| {
+            let subset: Vec<MpPartialDecryption<R>> = partial_decryptions.iter()
+                .filter(|d| set.contains(&d.name))
+                .cloned()
+                .collect();
+            self.mp_fin_dec(&subset)
+        })
+    }
+
+    /// Partial decryptions are untrusted. One is well formed if it has `num_rows` rows
+    /// with one share per share id, distinct ids of existing shares
+    /// and a reported noise bound that passes `check_flooded_noise` for `window`.
+    fn is_well_formed(&self, share_ids: &[u8], row_lens: impl ExactSizeIterator<Item = usize>, num_rows: usize, noise_bound: u64, window: u64) -> bool {
+        self.has_valid_shape(share_ids, row_lens, num_rows) && self.check_flooded_noise(noise_bound, window).is_ok()
+    }
+
+    /// Shape part of `is_well_formed`, without it combining the shares can panic
+    fn has_valid_shape(&self, share_ids: &[u8], mut row_lens: impl ExactSizeIterator<Item = usize>, num_rows: usize) -> bool {
+        let distinct: HashSet<u8> = share_ids.iter().copied().collect();
+        distinct.len() == share_ids.len()
+            && share_ids.iter().all(|id| (*id as usize) < self.secret_sharing_scheme.num_shares())
+            && row_lens.len() == num_rows
+            && row_lens.all(|len| len == share_ids.len())
+    }
+
+    /// Fails with `Inconsistent` if one of the partial decryptions does not have a valid shape
+    fn check_shapes(&self, shapes: impl Iterator<Item = (u8, bool)>) -> Result<(), TfheError> {
+        let mut malformed: Vec<u8> = shapes.filter(|(_, valid)| !valid).map(|(name, _)| name).collect();
+        if malformed.is_empty() {
+            return Ok(());
+        }
+        malformed.sort();
+        malformed.dedup();
+        Err(TfheError::Inconsistent(malformed))
+    }
+
+    /// Finds the smallest set of suspects C s.t. all minimal qualified sets avoiding C
+    /// decode to the same value. If suspect sets of that size lead to different values
+    /// the faulty parties cannot be identified.
+    /// Parties that are not well formed (or appear more than once) are excluded up front,
+    /// a set that fails to decode disagrees with every value.
+    fn robust_combine(&self, names: impl Iterator<Item = (u8, bool)>, decode: impl Fn(&HashSet<u8>) -> Result<R, TfheError>) -> Result<RobustDecryption<R>, TfheError> {
+        let mut all_names: HashSet<u8> = HashSet::new();
+        let mut malformed: HashSet<u8> = HashSet::new();
+        for (name, well_formed) in names {
+            if !all_names.insert(name) || !well_formed {
+                malformed.insert(name);
+            }
+        }
+        self.check_qualified(all_names.iter().copied())?;
+
+        let names: HashSet<u8> = all_names.difference(&malformed).copied().collect();
+        let mut malformed: Vec<u8> = malformed.into_iter().collect();
+        malformed.sort();
+        if !self.secret_sharing_scheme.is_qualified(&names) {
+            return Err(TfheError::Inconsistent(malformed));
+        }
+
+        let decoded: Vec<(HashSet<u8>, Option<R>)> = self.secret_sharing_scheme.min_qualified_sets(&names)?
+            .into_iter()
+            .map(|set| {
+                let value = decode(&set).ok();
+                (set, value)
+            })
+            .collect();
+
+        let mut sorted_names: Vec<u8> = names.into_iter().collect();
+        sorted_names.sort();
+
+        for num_suspects in 0..sorted_names.len() {
+            let mut candidates: Vec<(HashSet<u8>, R)> = vec![];
+            for suspects in subsets_of_size(&sorted_names, num_suspects) {
+                let mut remaining = decoded.iter()
+                    .filter(|(set, _)| set.is_disjoint(&suspects))
+                    .map(|(_, value)| *value);
+                if let Some(Some(first)) = remaining.next() && remaining.all(|value| value == Some(first)) {
+                    candidates.push((suspects, first));
+                }
+            }
+
+            if let Some((_, value)) = candidates.first() {
+                let value = *value;
+                if candidates.iter().any(|(_, v)| *v != value) {
+                    let mut suspects: Vec<u8> = candidates.into_iter().flat_map(|(c, _)| c).chain(malformed).collect();
+                    suspects.sort();
+                    suspects.dedup();
+                    return Err(TfheError::Inconsistent(suspects));
+                }
+
+                let mut inconsistent: Vec<u8> = sorted_names.iter()
+                    .filter(|p| !decoded.iter().any(|(set, v)| *v == Some(value) && set.contains(p)))
+                    .copied()
+                    .chain(malformed)
+                    .collect();
+                inconsistent.sort();
+                return Ok(RobustDecryption { value, inconsistent, checked_sets: decoded.len() });
+            }
+        }
+        sorted_names.extend(malformed);
+        sorted_names.sort();
+        Err(TfheError::Inconsistent(sorted_names))
+    }
+
+    fn check_qualified(&self, names: impl Iterator<Item = u8>) -> Result<(), TfheError> {
+        let names: HashSet<u8> = names.collect();
+        if self.secret_sharing_scheme.is_qualified(&names) {
+            Ok(())
+        } else {
+            let mut names: Vec<u8> = names.into_iter().collect();
+            names.sort();
+            Err(TfheError::Unqualified(names))
+        }
+    }
+}
+
+/// Implements GSW with arbitrary SSS scheme and arbitrary error distribition over any ring
+impl<R, S, T, F> TfheScheme<R> for TfheStructure<R, S, GSW<R, T>, F>
+where
+    R: RingElement + 'static,
+    S: SecretSharingScheme<R>,
+    T: ErrorSampling<R>,
+    F: ErrorSampling<R>,
+{
+    type SecretKey = <GSW<R, T> as FheScheme<R>>::SecretKey;
+    type PublicKey = <GSW<R, T> as FheScheme<R>>::PublicKey;
+    type Ciphertext = <GSW<R, T> as FheScheme<R>>::Ciphertext;
+    type KeyShare = KeyShare<R>;
+
+    /// Shares v = powers_of_2(s) since decryption is linear in v.
+    fn setup(&self) -> (Vec<Self::KeyShare>, Self::PublicKey) {
+        let (sk, pk) = self.fhe_scheme.keygen();
+        (KeyShare::share(&sk, &self.secret_sharing_scheme), pk)
+    }
+
+    /// # Parameters:
+    ///  - `pk`: Public Key
+    ///  - `message`: Ring element. Must be either R::zero() or R::one() (After Boneh et al.)
+    fn encrypt(&self, pk: &Self::PublicKey, message: R) -> Self::Ciphertext {
+        self.fhe_scheme.encrypt(pk, message)
+    }
+
+    /// The party computes <row, w_j> + smudging noise for every key share w_j it holds,
+    /// where row is the decryption row of the ciphertext.
+    /// Summing the (deduplicated) outputs of a qualified set yields <row, v> + noise.
+    /// The flooding noise hides the GSW error (and thereby the key shares), see `check_smudging`.
+    /// Fails if the ciphertext was not encrypted under pk (see `GSW::check_pk`)
+    /// or the ciphertext noise plus the flooding noise can exceed the decoding window.
+    fn part_dec(&self, pk: &Self::PublicKey, ciphertext: &Self::Ciphertext, key_share: &Self::KeyShare) -> Result<PartialDecryption<R>, TfheError> {
+        self.fhe_scheme.check_pk(pk, ciphertext)?;
+        self.check_flooded_noise(ciphertext.noise_bound(), self.fhe_scheme.noise_window())?;
+        let row = self.fhe_scheme.decryption_row(ciphertext);
+        let shares = key_share.dot(&row)
+            .into_iter()
+            .map(|d| d + self.smudging.rnd_error_elm())
+            .collect();
+
+        Ok(PartialDecryption { name: key_share.name, share_ids: key_share.share_ids.clone(), shares, noise_bound: ciphertext.noise_bound() })
+    }
+
+    /// Checks that the contributing parties are qualified, their partial decryptions have a valid shape
+    /// (`Inconsistent` otherwise) and the noise stays within the decoding window,
+    /// sums their deduplicated contributions to <row, v> (see `mbf_combine`) and rounds the result.
+    fn fin_dec(&self, partial_decryptions: &[PartialDecryption<R>]) -> Result<R, TfheError> {
+        self.check_qualified(partial_decryptions.iter().map(|d| d.name))?;
+        self.check_shapes(partial_decryptions.iter().map(|d| (d.name, self.has_valid_shape(&d.share_ids, std::iter::once(d.shares.len()), 1))))?;
+        let noise_bound = partial_decryptions.iter().map(|d| d.noise_bound).max().unwrap_or(0);
+        self.check_flooded_noise(noise_bound, self.fhe_scheme.noise_window())?;
+
+        let parties = partial_decryptions.iter()
+            .map(|d| Party::new(d.name, d.share_ids.clone(), vec![d.shares.clone()]))
+            .collect();
+        let row_dot_v = self.secret_sharing_scheme.combine(parties, false)[0];
+        let gadget = self.fhe_scheme.gadget();
+        Ok(round_to_bit(row_dot_v, gadget.power(gadget.num_digits::<R>() - 1)))
+    }
+
+    /// Same as `part_dec` but for each of the first l gadget rows (see `mp_decrypt`),
+    /// checked against the multi-bit decoding window
+    fn mp_part_dec(&self, pk: &Self::PublicKey, ciphertext: &Self::Ciphertext, key_share: &Self::KeyShare) -> Result<MpPartialDecryption<R>, TfheError> {
+        self.fhe_scheme.check_pk(pk, ciphertext)?;
+        self.check_flooded_noise(ciphertext.noise_bound(), self.fhe_scheme.mp_noise_window())?;
+        let rows = self.fhe_scheme.mp_decryption_rows(ciphertext);
+        let shares = rows.row_iter()
+            .map(|row| key_share.dot(&row.transpose())
+                .into_iter()
+                .map(|d| d + self.smudging.rnd_error_elm())
+                .collect())
+            .collect();
+
+        Ok(MpPartialDecryption { name: key_share.name, share_ids: key_share.share_ids.clone(), shares, noise_bound: ciphertext.noise_bound() })
+    }
+
+    /// Combines the contributions to every gadget row and recovers
+    /// the message from the summed values (see `mp_decrypt`).
+    fn mp_fin_dec(&self, partial_decryptions: &[MpPartialDecryption<R>]) -> Result<R, TfheError> {
+        self.check_qualified(partial_decryptions.iter().map(|d| d.name))?;
+        let num_rows = self.fhe_scheme.gadget().num_digits::<R>();
+        self.check_shapes(partial_decryptions.iter().map(|d| (d.name, self.has_valid_shape(&d.share_ids, d.shares.iter().map(Vec::len), num_rows))))?;
+        let noise_bound = partial_decryptions.iter().map(|d| d.noise_bound).max().unwrap_or(0);
+        self.check_flooded_noise(noise_bound, self.fhe_scheme.mp_noise_window())?;
+
+        let parties = partial_decryptions.iter()
+            .map(|d| Party::new(d.name, d.share_ids.clone(), d.shares.clone()))
+            .collect();
+        let rows_dot_v = self.secret_sharing_scheme.combine(parties, false);
+        Ok(recover_message(&rows_dot_v, self.fhe_scheme.gadget()))
+    }
+
+    fn add(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError> {
+        self.fhe_scheme.add(ciphertext1, ciphertext2)
+    }
+
+    fn mult_const(&self, ciphertext: &mut Self::Ciphertext, constant: R) -> Result<(), TfheError> {
+        self.fhe_scheme.mult_const(ciphertext, constant)
+    }
+
+    fn mult(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError> {
+        self.fhe_scheme.mult(ciphertext1, ciphertext2)
+    }
+
+    fn nand(&self, ciphertext1: &Self::Ciphertext, ciphertext2: &Self::Ciphertext) -> Result<Self::Ciphertext, TfheError> {
+        self.fhe_scheme.nand(ciphertext1, ciphertext2)
+    }
+}
+
+
+fn subsets_of_size(names: &[u8], size: usize) -> Vec<HashSet<u8>> {
+    if size == 0 {
+        return vec![HashSet::new()];
+    }
+    (0..names.len())
+        .flat_map(|i| subsets_of_size(&names[i + 1..], size - 1)
+            .into_iter()
+            .map(move |mut subset| {
+                subset.insert(names[i]);
+                subset
+            }))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use nalgebra::DVector;
+    use num_traits::{One, Zero};
+    use rand::Rng;
+
+    use crate::{error::TfheError, error_sampling::{DiscrGaussianSampler, ErrorSampling, UniformSampler}, field::Fp, gsw::{gadget::Gadget, key_share::KeyShare, params::SecurityLevel, sk::GswSk, FheScheme, GSW}, pow2_ring::Zpow2, zo_sss::{dimacs::{DIMACS, DIMACS_2_OF_3_SCHEME, DIMACS_2_OF_4_SCHEME, DIMACS_AB_OR_CD}, Party, SecretSharingScheme, MBF}, MpPartialDecryption, PartialDecryption, RingElement, RobustDecryption, TfheScheme, TfheStructure};
+
+    type TfheGsw<R> = TfheStructure<R, MBF, GSW<R, DiscrGaussianSampler>, UniformSampler>;
+
+    /// Uses half of the decoding window for flooding noise
+    fn build_tfhe<R: RingElement + 'static>(dimacs: &str) -> TfheGsw<R> {
+        let gsw = GSW::<R, DiscrGaussianSampler>::preset(SecurityLevel::Toy).unwrap();
+        let sss = MBF::new(DIMACS::parse(dimacs));
+        let smudging = UniformSampler::for_shares(R::max_u64() / 8, SecretSharingScheme::<R>::num_shares(&sss));
+        TfheStructure::new(sss, gsw, smudging)
+    }
+
+    #[test]
+    fn tfhe_gsw_fp_2_of_3() {
+        test_gates(&build_tfhe::<Fp>(DIMACS_2_OF_3_SCHEME));
+    }
+
+    #[test]
+    fn tfhe_gsw_pow2_ab_or_cd() {
+        test_gates(&build_tfhe::<Zpow2<31>>(DIMACS_AB_OR_CD));
+    }
+
+    fn test_gates<R: RingElement + 'static>(tfhe: &TfheGsw<R>) {
+        let (parties, pk) = tfhe.setup();
+        let sk = reconstruct_sk(tfhe, &parties);
+
+        let zero = tfhe.encrypt(&pk, R::zero());
+        let one = tfhe.encrypt(&pk, R::one());
+        assert_eq!(tfhe.fhe_scheme.decrypt(&sk, &zero), R::zero());
+        assert_eq!(tfhe.fhe_scheme.decrypt(&sk, &one), R::one());
+
+        assert_eq!(tfhe.fhe_scheme.decrypt(&sk, &tfhe.add(&zero, &one).unwrap()), R::one());
+        assert_eq!(tfhe.fhe_scheme.decrypt(&sk, &tfhe.mult(&one, &one).unwrap()), R::one());
+        assert_eq!(tfhe.fhe_scheme.decrypt(&sk, &tfhe.mult(&zero, &one).unwrap()), R::zero());
+
+        for (a, b, expected) in [(&zero, &zero, R::one()), (&zero, &one, R::one()), (&one, &zero, R::one()), (&one, &one, R::zero())] {
+            assert_eq!(tfhe.fhe_scheme.decrypt(&sk, &tfhe.nand(a, b).unwrap()), expected);
+        }
+
+        let mut scaled = one.clone();
+        tfhe.mult_const(&mut scaled, R::zero()).unwrap();
+        assert_eq!(tfhe.fhe_scheme.decrypt(&sk, &scaled), R::zero());
+    }
+
+    /// Recombines the shares of v and recovers t from
+    /// s_k = v[k * l] (first gadget entry is 1) and t = -s[1..].
+    fn reconstruct_sk<R: RingElement + 'static, F: ErrorSampling<R>>(tfhe: &TfheStructure<R, MBF, GSW<R, DiscrGaussianSampler>, F>, key_shares: &[KeyShare<R>]) -> GswSk<R> {
+        let v = KeyShare::combine(key_shares, &tfhe.secret_sharing_scheme);
+        let v = v.expose();
+        let gadget = tfhe.fhe_scheme.gadget();
+        let t = DVector::from_iterator(tfhe.fhe_scheme.n, (1..=tfhe.fhe_scheme.n).map(|k| -v[k * gadget.num_digits::<R>()]));
+        let sk = GswSk::new(t, gadget);
+        assert_eq!(sk.v().as_slice(), v.as_slice());
+        sk
+    }
+
+    #[test]
+    fn part_dec_sums_to_decryption_row() {
+        let tfhe = build_tfhe::<Fp>(DIMACS_2_OF_3_SCHEME);
+        // no flooding -> combination is exact
+        let tfhe = TfheStructure::new(tfhe.secret_sharing_scheme, tfhe.fhe_scheme, UniformSampler::new(0));
+        let (parties, pk) = tfhe.setup();
+        let ct = tfhe.encrypt(&pk, Fp::ONE);
+
+        let part_decs: Vec<PartialDecryption<Fp>> = parties.iter()
+            .map(|p| tfhe.part_dec(&pk, &ct, p).unwrap())
+            .collect();
+        for (p, d) in parties.iter().zip(part_decs.iter()) {
+            assert_eq!(p.name, d.name);
+            assert_eq!(p.share_ids, d.share_ids);
+            assert_eq!(p.shares().len(), d.shares.len());
+        }
+
+        // combining the partial decryptions like a single secret gives <row, v>
+        let as_parties = part_decs.into_iter()
+            .map(|d| Party::new(d.name, d.share_ids, vec![d.shares]))
+            .collect();
+        let combined = tfhe.secret_sharing_scheme.combine(as_parties, false)[0];
+
+        let sk = reconstruct_sk(&tfhe, &parties);
+        assert_eq!(combined, tfhe.fhe_scheme.decryption_row(&ct).dot(sk.v()));
+    }
+
+    #[test]
+    fn part_dec_is_flooded() {
+        let tfhe = build_tfhe::<Fp>(DIMACS_2_OF_3_SCHEME);
+        let (parties, pk) = tfhe.setup();
+        let ct = tfhe.encrypt(&pk, Fp::ONE);
+
+        let first = tfhe.part_dec(&pk, &ct, &parties[0]).unwrap();
+        let second = tfhe.part_dec(&pk, &ct, &parties[0]).unwrap();
+        assert_ne!(first, second);
+
+        let sk = reconstruct_sk(&tfhe, &parties);
+        let exact = tfhe.fhe_scheme.decryption_row(&ct).dot(sk.v());
+        let part_decs: Vec<PartialDecryption<Fp>> = parties.iter()
+            .map(|p| tfhe.part_dec(&pk, &ct, p).unwrap())
+            .collect();
+        let as_parties = part_decs.into_iter()
+            .map(|d| Party::new(d.name, d.share_ids, vec![d.shares]))
+            .collect();
+        let diff = tfhe.secret_sharing_scheme.combine(as_parties, false)[0] - exact;
+        let abs = std::cmp::min(Into::<u64>::into(diff), Into::<u64>::into(-diff));
+        assert!(abs <= 3 * ErrorSampling::<Fp>::error_bound(&tfhe.smudging));
+    }
+
+    #[test]
+    fn fin_dec_qualified_and_unqualified() {
+        // (1 or 3) and (1 or 4) and (2 or 3) and (2 or 4) <=> {1,2} or {3,4}
+        let tfhe = build_tfhe::<Zpow2<31>>(DIMACS_AB_OR_CD);
+        let (parties, pk) = tfhe.setup();
+        let zero = tfhe.encrypt(&pk, Zpow2::zero());
+        let one = tfhe.encrypt(&pk, Zpow2::one());
+
+        for (ct, expected) in [(&zero, Zpow2::zero()), (&one, Zpow2::one()), (&tfhe.nand(&one, &one).unwrap(), Zpow2::zero())] {
+            let part_decs: Vec<PartialDecryption<Zpow2<31>>> = parties.iter()
+                .map(|p| tfhe.part_dec(&pk, ct, p).unwrap())
+                .collect();
+
+            assert_eq!(tfhe.fin_dec(&part_decs), Ok(expected));
+            assert_eq!(tfhe.fin_dec(&part_decs[..2]), Ok(expected));
+            assert_eq!(tfhe.fin_dec(&part_decs[2..]), Ok(expected));
+            assert_eq!(tfhe.fin_dec(&part_decs[1..3]), Err(TfheError::Unqualified(vec![2, 3])));
+        }
+    }
+
+    #[test]
+    fn noisy_ciphertexts_are_rejected() {
+        let tfhe = build_tfhe::<Zpow2<31>>(DIMACS_AB_OR_CD);
+        let (parties, pk) = tfhe.setup();
+        let mut noisy = tfhe.encrypt(&pk, Zpow2::one());
+        tfhe.mult_const(&mut noisy, Zpow2::from(1 << 20)).unwrap();
+        assert!(matches!(tfhe.part_dec(&pk, &noisy, &parties[0]), Err(TfheError::NoiseTooLarge { .. })));
+        assert!(matches!(tfhe.mp_part_dec(&pk, &noisy, &parties[0]), Err(TfheError::NoiseTooLarge { .. })));
+
+        // fin_dec checks the reported noise bounds as well
+        let ct = tfhe.encrypt(&pk, Zpow2::one());
+        let mut part_decs: Vec<PartialDecryption<Zpow2<31>>> = parties.iter()
+            .map(|p| tfhe.part_dec(&pk, &ct, p).unwrap())
+            .collect();
+        part_decs[1].noise_bound = noisy.noise_bound();
+        assert_eq!(tfhe.fin_dec(&part_decs[2..]), Ok(Zpow2::one()));
+        assert!(matches!(tfhe.fin_dec(&part_decs[..2]), Err(TfheError::NoiseTooLarge { .. })));
+    }
+
+    #[test]
+    fn part_dec_checks_key() {
+        let tfhe = build_tfhe::<Zpow2<31>>(DIMACS_AB_OR_CD);
+        let (parties, pk) = tfhe.setup();
+        let (_, other_pk) = tfhe.setup();
+        let ct = tfhe.encrypt(&other_pk, Zpow2::one());
+        assert_eq!(tfhe.part_dec(&pk, &ct, &parties[0]), Err(TfheError::IncompatibleCiphertexts));
+        assert_eq!(tfhe.mp_part_dec(&pk, &ct, &parties[0]), Err(TfheError::IncompatibleCiphertexts));
+
+        // trivial encryptions are valid under every key
+        let trivial = tfhe.fhe_scheme.trivial_encrypt(Zpow2::one());
+        let part_decs: Vec<PartialDecryption<Zpow2<31>>> = parties.iter()
+            .map(|p| tfhe.part_dec(&pk, &trivial, p).unwrap())
+            .collect();
+        assert_eq!(tfhe.fin_dec(&part_decs), Ok(Zpow2::one()));
+    }
+
+    #[test]
+    fn mp_fin_dec_prime() {
+        let tfhe = build_tfhe::<Fp>(DIMACS_2_OF_3_SCHEME);
+        let (parties, pk) = tfhe.setup();
+
+        let mut rng = rand::rng();
+        for msg in [Fp::ZERO, -Fp::ONE, Fp::from(rng.random_range(0..Fp::max_u64()))] {
+            let ct = tfhe.encrypt(&pk, msg);
+            let part_decs: Vec<MpPartialDecryption<Fp>> = parties.iter()
+                .map(|p| tfhe.mp_part_dec(&pk, &ct, p).unwrap())
+                .collect();
+            assert_eq!(tfhe.mp_fin_dec(&part_decs), Ok(msg));
+            assert_eq!(tfhe.mp_fin_dec(&part_decs[1..]), Ok(msg));
+        }
+    }
+
+    #[test]
+    fn mp_fin_dec_pow2() {
+        let tfhe = build_tfhe::<Zpow2<31>>(DIMACS_AB_OR_CD);
+        let (parties, pk) = tfhe.setup();
+
+        let mut rng = rand::rng();
+        for _ in 0..5 {
+            let msg = Zpow2::<31>::from(rng.random_range(0..Zpow2::<31>::max_u64()));
+            let ct = tfhe.encrypt(&pk, msg);
+            let part_decs: Vec<MpPartialDecryption<Zpow2<31>>> = parties.iter()
+                .map(|p| tfhe.mp_part_dec(&pk, &ct, p).unwrap())
+                .collect();
+            assert!(part_decs.iter().all(|d| d.shares.len() == Zpow2::<31>::Num_Bits));
+
+            assert_eq!(tfhe.mp_fin_dec(&part_decs), Ok(msg));
+            assert_eq!(tfhe.mp_fin_dec(&part_decs[2..]), Ok(msg));
+            assert_eq!(tfhe.mp_fin_dec(&part_decs[..1]), Err(TfheError::Unqualified(vec![1])));
+        }
+    }
+
+    #[test]
+    fn robust_fin_dec_detects_faulty_party() {
+        let tfhe = build_tfhe::<Fp>(DIMACS_2_OF_4_SCHEME);
+        let (parties, pk) = tfhe.setup();
+        let ct = tfhe.encrypt(&pk, Fp::ONE);
+        let mut part_decs: Vec<PartialDecryption<Fp>> = parties.iter()
+            .map(|p| tfhe.part_dec(&pk, &ct, p).unwrap())
+            .collect();
+
+        let honest = tfhe.robust_fin_dec(&part_decs).unwrap();
+        assert_eq!(honest, RobustDecryption { value: Fp::ONE, inconsistent: vec![], checked_sets: 6 });
+
+        // shift party 4's contribution by q/2
+        part_decs[3].shares.iter_mut().for_each(|d| *d += Fp::from(Fp::max_u64() / 2));
+        let robust = tfhe.robust_fin_dec(&part_decs).unwrap();
+        assert_eq!(robust.value, Fp::ONE);
+        assert_eq!(robust.inconsistent, vec![4]);
+
+        // no redundancy left without an honest third party
+        let faulty_pair = [part_decs[0].clone(), part_decs[3].clone()];
+        assert_eq!(tfhe.robust_fin_dec(&faulty_pair).unwrap().checked_sets, 1);
+    }
+
+    #[test]
+    fn robust_fin_dec_excludes_malformed_parties() {
+        let tfhe = build_tfhe::<Zpow2<31>>(DIMACS_2_OF_4_SCHEME);
+        let (parties, pk) = tfhe.setup();
+        let ct = tfhe.encrypt(&pk, Zpow2::one());
+        let mut part_decs: Vec<PartialDecryption<Zpow2<31>>> = parties.iter()
+            .map(|p| tfhe.part_dec(&pk, &ct, p).unwrap())
+            .collect();
+        // party 2 forges its noise bound, party 3 sends truncated shares
+        part_decs[1].noise_bound = u64::MAX;
+        part_decs[2].shares.pop();
+
+        assert_eq!(tfhe.fin_dec(&part_decs[2..]), Err(TfheError::Inconsistent(vec![3])));
+        assert_eq!(tfhe.robust_fin_dec(&part_decs), Ok(RobustDecryption { value: Zpow2::one(), inconsistent: vec![2, 3], checked_sets: 1 }));
+        assert_eq!(tfhe.robust_fin_dec(&part_decs[1..3]), Err(TfheError::Inconsistent(vec![2, 3])));
+
+        // the same for the rows of multi-bit partial decryptions
+        let mut mp_part_decs: Vec<MpPartialDecryption<Zpow2<31>>> = parties.iter()
+            .map(|p| tfhe.mp_part_dec(&pk, &ct, p).unwrap())
+            .collect();
+        mp_part_decs[1].noise_bound = u64::MAX;
+        mp_part_decs[2].shares.truncate(3);
+        mp_part_decs[3].shares[0].pop();
+
+        assert_eq!(tfhe.mp_fin_dec(&mp_part_decs[2..]), Err(TfheError::Inconsistent(vec![3, 4])));
+        let robust = tfhe.robust_mp_fin_dec(&mp_part_decs[..2]);
+        assert_eq!(robust, Err(TfheError::Inconsistent(vec![2])));
+        let mut valid = mp_part_decs.clone();
+        valid[1].noise_bound = ct.noise_bound();
+        assert_eq!(tfhe.robust_mp_fin_dec(&valid).unwrap().inconsistent, vec![3, 4]);
+    }
+
+    #[test]
+    fn robust_fin_dec_ambiguous() {
+        // each pair is qualified -> a single faulty party out of three cannot be identified
+        let tfhe = build_tfhe::<Fp>(DIMACS_2_OF_3_SCHEME);
+        let (parties, pk) = tfhe.setup();
+        let ct = tfhe.encrypt(&pk, Fp::ZERO);
+        let mut part_decs: Vec<PartialDecryption<Fp>> = parties.iter()
+            .map(|p| tfhe.part_dec(&pk, &ct, p).unwrap())
+            .collect();
+        part_decs[2].shares.iter_mut().for_each(|d| *d += Fp::from(Fp::max_u64() / 2));
+
+        assert!(matches!(tfhe.robust_fin_dec(&part_decs), Err(TfheError::Inconsistent(_))));
+    }
+
+    #[test]
+    fn robust_mp_fin_dec_detects_faulty_party() {
+        let tfhe = build_tfhe::<Zpow2<31>>(DIMACS_2_OF_4_SCHEME);
+        let (parties, pk) = tfhe.setup();
+        let msg = Zpow2::<31>::from(123456);
+        let ct = tfhe.encrypt(&pk, msg);
+        let mut part_decs: Vec<MpPartialDecryption<Zpow2<31>>> = parties.iter()
+            .map(|p| tfhe.mp_part_dec(&pk, &ct, p).unwrap())
+            .collect();
+        part_decs[1].shares.iter_mut().flatten().for_each(|d| *d += Zpow2::from(Zpow2::<31>::max_u64() / 2));
+
+        let robust = tfhe.robust_mp_fin_dec(&part_decs).unwrap();
+        assert_eq!(robust.value, msg);
+        assert_eq!(robust.inconsistent, vec![2]);
+    }
+
+    #[test]
+    fn refresh_keeps_ciphertexts_valid() {
+        let tfhe = build_tfhe::<Zpow2<31>>(DIMACS_AB_OR_CD);
+        let (mut key_shares, pk) = tfhe.setup();
+        let ct = tfhe.encrypt(&pk, Zpow2::one());
+        let old = key_shares.clone();
+
+        tfhe.refresh_key_shares(&mut key_shares);
+        assert!(old.iter().zip(key_shares.iter()).all(|(o, n)| o.shares() != n.shares()));
+
+        let ct_new = tfhe.encrypt(&pk, Zpow2::zero());
+        for (ct, expected) in [(&ct, Zpow2::one()), (&ct_new, Zpow2::zero())] {
+            let part_decs: Vec<PartialDecryption<Zpow2<31>>> = key_shares.iter()
+                .map(|ks| tfhe.part_dec(&pk, ct, ks).unwrap())
+                .collect();
+            assert_eq!(tfhe.fin_dec(&part_decs[..2]), Ok(expected));
+            assert_eq!(tfhe.fin_dec(&part_decs[2..]), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn smudging_parameter_check() {
+        let tfhe = build_tfhe::<Fp>(DIMACS_2_OF_3_SCHEME);
+        let noise_bound = tfhe.fhe_scheme.fresh_noise_bound();
+        assert_eq!(tfhe.check_smudging(noise_bound), Ok(()));
+
+        // every single share is within the window but their sum is not
+        let too_large = UniformSampler::new(Fp::max_u64() / 8);
+        let tfhe = TfheStructure::new(tfhe.secret_sharing_scheme, tfhe.fhe_scheme, too_large);
+        assert!(matches!(tfhe.check_smudging(noise_bound), Err(TfheError::SmudgingTooLarge { .. })));
+        let (parties, pk) = tfhe.setup();
+        let ct = tfhe.encrypt(&pk, Fp::ONE);
+        assert!(matches!(tfhe.part_dec(&pk, &ct, &parties[0]), Err(TfheError::SmudgingTooLarge { .. })));
+
+        // base 4 over Fp: the flooding fits the window of decrypt (2^29) but not the one of mp_decrypt (q/10)
+        let gsw = GSW::<Fp, DiscrGaussianSampler>::new(4, 4 * Fp::Num_Bits, DiscrGaussianSampler::default(), Gadget::new(2, false)).unwrap();
+        let smudging = UniformSampler::for_shares(Fp::max_u64() / 7, 3);
+        let tfhe = TfheStructure::new(MBF::new(DIMACS::parse(DIMACS_2_OF_3_SCHEME)), gsw, smudging);
+        assert!(tfhe.flooded_noise_bound(noise_bound) < tfhe.fhe_scheme.noise_window());
+        assert!(matches!(tfhe.check_smudging(noise_bound), Err(TfheError::SmudgingTooLarge { window, .. }) if window == tfhe.fhe_scheme.mp_noise_window()));
+        let (parties, pk) = tfhe.setup();
+        let ct = tfhe.encrypt(&pk, Fp::ONE);
+        assert!(tfhe.part_dec(&pk, &ct, &parties[0]).is_ok());
+        assert!(matches!(tfhe.mp_part_dec(&pk, &ct, &parties[0]), Err(TfheError::SmudgingTooLarge { .. })));
+    }
+}
@@ -0,0 +1,211 @@
+use std::convert::Infallible;
+
+use crate::{error::TfheError, error_sampling::ErrorSampling, gsw::{ciphertext::GswCiphertext, key_share::KeyShare, pk::GswPk, FheScheme, GSW}, zo_sss::SecretSharingScheme, PartialDecryption, RingElement, TfheScheme, TfheStructure};
+
+
+/// Boolean circuit consisting of NAND gates only.
+/// Wires 0..num_inputs are the inputs, wire num_inputs + k is the output of gates[k].
+/// Every gate may only use wires that are defined before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NandCircuit {
+    pub num_inputs: usize,
+    pub gates: Vec<(usize, usize)>,
+    pub outputs: Vec<usize>,
+}
+
+impl NandCircuit {
+    pub fn new(num_inputs: usize, gates: Vec<(usize, usize)>, outputs: Vec<usize>) -> Self {
+        for (k, (a, b)) in gates.iter().enumerate() {
+            assert!(*a < num_inputs + k && *b < num_inputs + k, "Gate {} uses an undefined wire", k);
+        }
+        assert!(outputs.iter().all(|o| *o < num_inputs + gates.len()), "Output uses an undefined wire");
+        Self { num_inputs, gates, outputs }
+    }
+
+    /// Evaluates the circuit on plaintext bits
+    pub fn eval(&self, inputs: &[bool]) -> Vec<bool> {
+        self.eval_with(inputs.to_vec(), |a, b| !(*a && *b))
+    }
+
+    /// Evaluates the circuit with an arbitrary NAND implementation
+    pub fn eval_with<W: Clone>(&self, inputs: Vec<W>, nand: impl Fn(&W, &W) -> W) -> Vec<W> {
+        let res: Result<Vec<W>, Infallible> = self.try_eval_with(inputs, |a, b| Ok(nand(a, b)));
+        res.unwrap_or_else(|never| match never {})
+    }
+
+    /// Evaluates the circuit with a fallible NAND implementation, stopping at the first error
+    pub fn try_eval_with<W: Clone, E>(&self, inputs: Vec<W>, nand: impl Fn(&W, &W) -> Result<W, E>) -> Result<Vec<W>, E> {
+        assert_eq!(inputs.len(), self.num_inputs, "Wrong number of inputs");
+        let mut wires = inputs;
+        wires.reserve(self.gates.len());
+        for (a, b) in self.gates.iter() {
+            let out = nand(&wires[*a], &wires[*b])?;
+            wires.push(out);
+        }
+        Ok(self.outputs.iter().map(|o| wires[*o].clone()).collect())
+    }
+}
+
+/// Public parameters of a thresholdized secret:
+/// the TFHE public key and the encryption of every secret bit under it
+pub struct ThresholdizedSecret<R: RingElement> {
+    pub pk: GswPk<R>,
+    pub encrypted_secret: Vec<GswCiphertext<R>>,
+}
+
+/// Universal thresholdizer of Boneh et al.
+/// Thresholdizes f(secret, input) for any f given as `NandCircuit` whose first
+/// inputs are the secret bits followed by the public input bits.
+pub struct Thresholdizer<R, S, T, F>
+where
+    R: RingElement + 'static,
+    S: SecretSharingScheme<R>,
+    T: ErrorSampling<R>,
+    F: ErrorSampling<R>,
+{
+    pub tfhe: TfheStructure<R, S, GSW<R, T>, F>,
+}
+
+impl<R, S, T, F> Thresholdizer<R, S, T, F>
+where
+    R: RingElement + 'static,
+    S: SecretSharingScheme<R>,
+    T: ErrorSampling<R>,
+    F: ErrorSampling<R>,
+{
+    pub fn new(tfhe: TfheStructure<R, S, GSW<R, T>, F>) -> Self {
+        Self { tfhe }
+    }
+
+    /// Generates the key shares and encrypts the secret under the TFHE public key
+    pub fn setup(&self, secret: &[bool]) -> (Vec<KeyShare<R>>, ThresholdizedSecret<R>) {
+        let (key_shares, pk) = self.tfhe.setup();
+        let encrypted_secret = secret.iter()
+            .map(|bit| self.tfhe.encrypt(&pk, to_ring(*bit)))
+            .collect();
+        (key_shares, ThresholdizedSecret { pk, encrypted_secret })
+    }
+
+    /// Run by every party: evaluates the circuit homomorphically and partially decrypts every output bit.
+    /// Public input bits are encrypted trivially, so every party ends up with the same output ciphertexts.
+    /// Fails if the encrypted secret does not belong to the parameters of the scheme
+    /// or an output is too noisy to be decrypted (see `part_dec`).
+    pub fn eval(&self, public: &ThresholdizedSecret<R>, key_share: &KeyShare<R>, circuit: &NandCircuit, input: &[bool]) -> Result<Vec<PartialDecryption<R>>, TfheError> {
+        let inputs: Vec<GswCiphertext<R>> = public.encrypted_secret.iter()
+            .cloned()
+            .chain(input.iter().map(|bit| self.tfhe.fhe_scheme.trivial_encrypt(to_ring(*bit))))
+            .collect();
+
+        circuit.try_eval_with(inputs, |a, b| self.tfhe.fhe_scheme.nand(a, b))?
+            .iter()
+            .map(|ct| self.tfhe.part_dec(&public.pk, ct, key_share))
+            .collect()
+    }
+
+    /// Combines the outputs of `eval` of a qualified set of parties into f(secret, input).
+    /// The outputs are untrusted: fails with `Inconsistent` (naming the parties that sent them)
+    /// if they do not all have the same number of partial decryptions as the first one.
+    pub fn combine(&self, partial_evals: &[Vec<PartialDecryption<R>>]) -> Result<Vec<bool>, TfheError> {
+        let Some(first) = partial_evals.first() else {
+            return Err(TfheError::Unqualified(vec![]));
+        };
+        let num_outputs = first.len();
+        let mut mismatched: Vec<u8> = partial_evals.iter()
+            .filter(|p| p.len() != num_outputs)
+            .flat_map(|p| p.iter().map(|d| d.name))
+            .collect();
+        if partial_evals.iter().any(|p| p.len() != num_outputs) {
+            mismatched.sort();
+            mismatched.dedup();
+            return Err(TfheError::Inconsistent(mismatched));
+        }
+        (0..num_outputs)
+            .map(|k| {
+                let part_decs: Vec<PartialDecryption<R>> = partial_evals.iter()
+                    .map(|p| p[k].clone())
+                    .collect();
+                self.tfhe.fin_dec(&part_decs).map(|bit| bit.is_one())
+            })
+            .collect()
+    }
+}
+
+fn to_ring<R: RingElement>(bit: bool) -> R {
+    if bit { R::one() } else { R::zero() }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::TfheError, error_sampling::{DiscrGaussianSampler, UniformSampler}, gsw::{params::SecurityLevel, GSW}, pow2_ring::Zpow2, thresholdizer::{NandCircuit, Thresholdizer}, zo_sss::{dimacs::{DIMACS, DIMACS_2_OF_3_SCHEME}, MBF}, TfheStructure};
+
+    type Z31 = Zpow2<31>;
+
+    fn build_thresholdizer() -> Thresholdizer<Z31, MBF, DiscrGaussianSampler, UniformSampler> {
+        let gsw = GSW::<Z31, DiscrGaussianSampler>::preset(SecurityLevel::Toy).unwrap();
+        Thresholdizer::new(TfheStructure::new(MBF::new(DIMACS::parse(DIMACS_2_OF_3_SCHEME)), gsw, UniformSampler::for_shares(1 << 24, 3)))
+    }
+
+    /// inputs: secret bits (s0, s1), public bits (x0, x1)
+    /// outputs: (s0 NAND x0, s1 AND x1)
+    fn test_circuit() -> NandCircuit {
+        NandCircuit::new(4, vec![(0, 2), (1, 3), (5, 5)], vec![4, 6])
+    }
+
+    #[test]
+    fn plain_circuit() {
+        let circuit = test_circuit();
+        assert_eq!(circuit.eval(&[true, true, true, true]), vec![false, true]);
+        assert_eq!(circuit.eval(&[false, true, true, false]), vec![true, false]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn circuit_with_undefined_wire() {
+        NandCircuit::new(2, vec![(0, 2)], vec![2]);
+    }
+
+    #[test]
+    fn thresholdized_eval() {
+        let thresholdizer = build_thresholdizer();
+        let circuit = test_circuit();
+        let secret = [true, true];
+        let (key_shares, public) = thresholdizer.setup(&secret);
+
+        for input in [[true, true], [false, true], [true, false]] {
+            let partial_evals: Vec<_> = key_shares.iter()
+                .map(|ks| thresholdizer.eval(&public, ks, &circuit, &input).unwrap())
+                .collect();
+
+            let expected = circuit.eval(&[secret[0], secret[1], input[0], input[1]]);
+            assert_eq!(thresholdizer.combine(&partial_evals), Ok(expected.clone()));
+            assert_eq!(thresholdizer.combine(&partial_evals[1..]), Ok(expected));
+            assert_eq!(thresholdizer.combine(&partial_evals[..1]), Err(TfheError::Unqualified(vec![1])));
+        }
+        assert_eq!(thresholdizer.combine(&[]), Err(TfheError::Unqualified(vec![])));
+
+        // a party that drops an output is reported instead of causing a panic
+        let mut partial_evals: Vec<_> = key_shares.iter()
+            .map(|ks| thresholdizer.eval(&public, ks, &circuit, &[true, true]).unwrap())
+            .collect();
+        partial_evals[2].pop();
+        assert_eq!(thresholdizer.combine(&partial_evals), Err(TfheError::Inconsistent(vec![3])));
+    }
+
+    #[test]
+    fn noisy_outputs_are_rejected() {
+        let thresholdizer = build_thresholdizer();
+        // acc <- NAND(acc, s) six times: the fresh secret bit is the right factor, so the noise grows
+        // linearly with flattening and beyond the decoding window after two gates without
+        let circuit = NandCircuit::new(1, (0..6).map(|k| (k, 0)).collect(), vec![6]);
+        let (key_shares, public) = thresholdizer.setup(&[true]);
+
+        let partial_evals: Result<Vec<_>, _> = key_shares.iter()
+            .map(|ks| thresholdizer.eval(&public, ks, &circuit, &[]))
+            .collect();
+        #[cfg(feature="use_flatten")]
+        assert_eq!(thresholdizer.combine(&partial_evals.unwrap()), Ok(vec![true]));
+        #[cfg(not(feature="use_flatten"))]
+        assert!(matches!(partial_evals, Err(TfheError::NoiseTooLarge { .. })));
+    }
+}
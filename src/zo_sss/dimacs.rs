@@ -10,6 +10,17 @@ p cnf 3 3
 2 3 0
 ";
 
+/// (A or B or C) and (A or B or D) and (A or C or D) and (B or C or D)
+/// i.e. any 2 out of 4 parties
+pub const DIMACS_2_OF_4_SCHEME: &str = "\
+c Example DIMACS CNF
+p cnf 4 4
+1 2 3 0
+1 2 4 0
+1 3 4 0
+2 3 4 0
+";
+
 pub const DIMACS_AB_OR_CD: &str = "\
 c Example non-k-of-n Access structure
 p cnf 4 4 
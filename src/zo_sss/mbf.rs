@@ -1,37 +1,42 @@
 use std::collections::HashSet;
 
-use crate::{error_sampling::rnd_ring_elm, field::{Fp, P}, zo_sss::{dimacs::DIMACS, Party}, RingElement};
+use zeroize::Zeroizing;
+
+use crate::{error::TfheError, error_sampling::rnd_uniform_ring_elm, secret::Secret, zo_sss::{dimacs::DIMACS, Party}, RingElement};
 
 /// Secret Sharing via Monotone Boolean Formula Access Structure
 /// Access Structure is fully defined via DIMACS.
+/// The secrets are wiped after sharing.
 /// # Parameters
 /// - `secrets`: A set of secrets in Fp.
 /// - `dimacs`: The monotone boolean formula (MBF) defining the access structure.
 pub fn mbf_share<R: RingElement>(secrets: Vec<R>, dimacs: &DIMACS) -> Vec<Party<R>> {
+    let secrets = Zeroizing::new(secrets);
     let num = dimacs.num_clauses as usize;
-    let w_matrix: Vec<Vec<R>> = build_w_matrix(secrets, num);
-    get_parties(w_matrix, dimacs)
+    let w_matrix = Secret::new(build_w_matrix(&secrets, num));
+    get_parties(w_matrix.expose(), dimacs)
 }
 
-fn get_parties<R: RingElement>(w_matrix: Vec<Vec<R>>, dimacs: &DIMACS) -> Vec<Party<R>> {
+fn get_parties<R: RingElement>(w_matrix: &[Vec<R>], dimacs: &DIMACS) -> Vec<Party<R>> {
     dimacs.partitions
         .iter()
         .enumerate()
         .map(|(i, p)| {
+            let share_ids: Vec<u8> = p.iter().copied().collect();
             let shares = w_matrix
                 .iter()
-                .map(|w| p.iter().map(|&j| w[j as usize]).collect())
+                .map(|w| share_ids.iter().map(|&j| w[j as usize]).collect())
                 .collect();
-            Party { name: (i + 1) as u8, shares }
+            Party::new((i + 1) as u8, share_ids, shares)
         })
         .collect()
 }
 
-fn build_w_matrix<R: RingElement>(secrets: Vec<R>, num: usize) -> Vec<Vec<R>> {
+fn build_w_matrix<R: RingElement>(secrets: &[R], num: usize) -> Vec<Vec<R>> {
     secrets
-        .into_iter()
+        .iter()
         .map(|secret| {
-            build_w(secret, num)
+            build_w(*secret, num)
         })
         .collect()
 }
@@ -66,13 +71,14 @@ fn build_w_matrix<R: RingElement>(secrets: Vec<R>, num: usize) -> Vec<Vec<R>> {
 /// or secret||random vector.
 fn build_w<R: RingElement>(secret: R, num: usize) -> Vec<R> {
     let mut v1 = secret;
-    let mut v2: R = rnd_ring_elm(0, P - 1);
+    // masks have to be uniform over the whole ring, or the shares leak the high bits of the secret
+    let mut v2: R = rnd_uniform_ring_elm();
     let mut w = Vec::with_capacity(num);
 
     for _ in 0..num - 1 {
         w.push(v1 + v2);
         v1 = -v2;
-        v2 = rnd_ring_elm(0, P - 1);
+        v2 = rnd_uniform_ring_elm();
     }
     w.push(v1);
     w
@@ -92,9 +98,9 @@ pub fn mbf_combine<R: RingElement>(parties: Vec<Party<R>>, is_minimal: bool, dim
         get_min_party(&parties, dimacs)
     } else { parties };
 
-    let num_secrets = min_set.first().unwrap().shares.len();
+    let num_secrets = min_set.first().unwrap().shares().len();
     (0..num_secrets)
-        .map(|i| get_party_shares(&min_set, i).iter().sum())
+        .map(|i| sum_party_shares(&min_set, i))
         .collect()
 }
 
@@ -103,16 +109,16 @@ pub fn get_min_party<R: RingElement>(parties: &Vec<Party<R>>, dimacs: &DIMACS) -
     get_parties_by_name(&parties, &min_set_names)
 }
 
-// Cannot use HashSet<Fp> bc Fp does not implement Hash
-// -> manual deduplication
-fn get_party_shares<R: RingElement>(parties: &Vec<Party<R>>, index: usize) -> Vec<R> {
-    let mut all_shares: Vec<R> = parties.iter()
-        .flat_map(|p| p.shares[index].iter().cloned())
-        .collect();
-
-    all_shares.sort();
-    all_shares.dedup();
-    all_shares
+// Shares held by multiple parties must only be counted once
+// -> deduplicate by share id (not by value, as shares may carry party-specific noise).
+// Sums in place, so no unwiped copies of the shares are made.
+fn sum_party_shares<R: RingElement>(parties: &Vec<Party<R>>, index: usize) -> R {
+    let mut seen: HashSet<u8> = HashSet::new();
+    parties.iter()
+        .flat_map(|p| p.share_ids.iter().zip(p.shares()[index].iter()))
+        .filter(|(id, _)| seen.insert(**id))
+        .map(|(_, share)| share)
+        .sum()
 }
 
 fn get_parties_by_name<R: RingElement>(parties: &Vec<Party<R>>, names: &HashSet<u8>) -> Vec<Party<R>> {
@@ -144,7 +150,59 @@ fn find_min_sat(party: HashSet<u8>, dimacs: &DIMACS) -> Option<HashSet<u8>> {
     }
 }
 
-fn check_sat(parties: &HashSet<u8>, dimacs: &DIMACS) -> bool {
+/// Upper bound on the number of sets `find_all_min_sat` enumerates
+pub const MAX_MIN_SETS: usize = 1 << 16;
+
+/// Finds all minimal subsets of `party` satisfying the access structure
+/// (`find_min_sat` only returns one of them).
+/// A satisfying set hits every clause, so the sets are built by branching on the parties of
+/// the first clause that is not hit yet, where the i-th branch excludes the parties of the
+/// previous ones. Every set is reached once and branches with a redundant party are cut.
+/// Fails if there are more than `MAX_MIN_SETS` minimal sets.
+pub fn find_all_min_sat(party: &HashSet<u8>, dimacs: &DIMACS) -> Result<Vec<HashSet<u8>>, TfheError> {
+    let mut min_sets = vec![];
+    extend_min_sat(&mut HashSet::new(), &mut HashSet::new(), party, dimacs, &mut min_sets)?;
+    Ok(min_sets)
+}
+
+fn extend_min_sat(chosen: &mut HashSet<u8>, excluded: &mut HashSet<u8>, party: &HashSet<u8>, dimacs: &DIMACS, min_sets: &mut Vec<HashSet<u8>>) -> Result<(), TfheError> {
+    // clauses that are not hit yet contain no chosen party, so redundant parties stay redundant
+    if !is_irredundant(chosen, dimacs) {
+        return Ok(());
+    }
+    let Some(clause) = dimacs.clauses.iter().find(|cl| !cl.iter().any(|p| chosen.contains(p))) else {
+        if min_sets.len() == MAX_MIN_SETS {
+            return Err(TfheError::InvalidParameters(format!(
+                "access structure has more than {} minimal qualified sets", MAX_MIN_SETS)));
+        }
+        min_sets.push(chosen.clone());
+        return Ok(());
+    };
+
+    let mut options: Vec<u8> = clause.iter()
+        .copied()
+        .filter(|p| party.contains(p) && !excluded.contains(p))
+        .collect();
+    options.sort();
+    options.dedup();
+    for &p in &options {
+        chosen.insert(p);
+        let res = extend_min_sat(chosen, excluded, party, dimacs, min_sets);
+        chosen.remove(&p);
+        res?;
+        excluded.insert(p);
+    }
+    options.iter().for_each(|p| { excluded.remove(p); });
+    Ok(())
+}
+
+/// Every party is the only member of the set in some clause, i.e. no party can be removed
+fn is_irredundant(parties: &HashSet<u8>, dimacs: &DIMACS) -> bool {
+    parties.iter().all(|p| dimacs.clauses.iter()
+        .any(|cl| cl.contains(p) && cl.iter().all(|q| q == p || !parties.contains(q))))
+}
+
+pub fn check_sat(parties: &HashSet<u8>, dimacs: &DIMACS) -> bool {
     for cl in &dimacs.clauses {
         if !parties.iter().any(|it| cl.contains(it)) {
             return false;
@@ -156,7 +214,11 @@ fn check_sat(parties: &HashSet<u8>, dimacs: &DIMACS) -> bool {
 #[cfg(test)]
 mod tests {
 
-    use crate::{error_sampling::rnd_ring_elm, field::{Fp, P}, pow2_ring::Zpow2, zo_sss::{dimacs::{DIMACS, DIMACS_2_OF_3_SCHEME, DIMACS_AB_OR_CD}, mbf::{get_min_party, mbf_combine, mbf_share}}, RingElement};
+    use std::collections::HashSet;
+
+    use num_traits::Zero;
+
+    use crate::{error::TfheError, error_sampling::rnd_ring_elm, field::{Fp, P}, pow2_ring::Zpow2, zo_sss::{dimacs::{DIMACS, DIMACS_2_OF_3_SCHEME, DIMACS_2_OF_4_SCHEME, DIMACS_AB_OR_CD}, mbf::{find_all_min_sat, get_min_party, mbf_combine, mbf_share}}, RingElement};
 
     #[test]
     fn share_test_two_of_three() {
@@ -165,7 +227,7 @@ mod tests {
         let parties = mbf_share(vec![secret], &dimacs);
         assert_eq!(parties.len(), 3);
         for p in parties {
-            assert_eq!(p.shares[0].len(), 2);
+            assert_eq!(p.shares()[0].len(), 2);
         }
     }
 
@@ -176,7 +238,7 @@ mod tests {
         let parties = mbf_share(vec![secret], &dimacs);
         assert_eq!(parties.len(), 4);
         for p in parties {
-            assert_eq!(p.shares[0].len(), 2);
+            assert_eq!(p.shares()[0].len(), 2);
         }
     }
 
@@ -194,6 +256,48 @@ mod tests {
         execute_mbf_test(secret, &dimacs);
     }
 
+    #[test]
+    fn all_min_sets() {
+        let dimacs = DIMACS::parse(DIMACS_AB_OR_CD);
+        let all: HashSet<u8> = (1..=4).collect();
+        let min_sets = find_all_min_sat(&all, &dimacs).unwrap();
+        assert_eq!(min_sets.len(), 2);
+        assert!(min_sets.contains(&HashSet::from([1, 2])));
+        assert!(min_sets.contains(&HashSet::from([3, 4])));
+
+        let dimacs = DIMACS::parse(DIMACS_2_OF_4_SCHEME);
+        assert_eq!(find_all_min_sat(&all, &dimacs).unwrap().len(), 6);
+        assert_eq!(find_all_min_sat(&HashSet::from([1, 2, 3]), &dimacs).unwrap().len(), 3);
+        assert!(find_all_min_sat(&HashSet::from([1]), &dimacs).unwrap().is_empty());
+
+        // 2 out of 40: one clause per party, containing all other parties
+        let clauses: String = (1..=40u8)
+            .map(|i| (1..=40u8).filter(|j| *j != i).map(|j| format!("{} ", j)).collect::<String>() + "0\n")
+            .collect();
+        let dimacs = DIMACS::parse(&format!("p cnf 40 40\n{}", clauses));
+        let all: HashSet<u8> = (1..=40).collect();
+        let min_sets = find_all_min_sat(&all, &dimacs).unwrap();
+        assert_eq!(min_sets.len(), 40 * 39 / 2);
+        assert!(min_sets.iter().all(|set| set.len() == 2));
+
+        // (1 or 2) and (3 or 4) and ... has 2^17 minimal sets
+        let clauses: String = (0..17u8).map(|i| format!("{} {} 0\n", 2 * i + 1, 2 * i + 2)).collect();
+        let dimacs = DIMACS::parse(&format!("p cnf 34 17\n{}", clauses));
+        let all: HashSet<u8> = (1..=34).collect();
+        assert!(matches!(find_all_min_sat(&all, &dimacs), Err(TfheError::InvalidParameters(_))));
+    }
+
+    #[test]
+    fn masks_cover_wide_rings() {
+        let dimacs = DIMACS::parse(DIMACS_2_OF_3_SCHEME);
+        let parties = mbf_share(vec![Zpow2::<40>::zero()], &dimacs);
+        let high = parties.iter()
+            .flat_map(|p| p.shares()[0].iter())
+            .any(|s| s.value() >= 1 << 31);
+        assert!(high);
+        assert_eq!(mbf_combine(parties, false, &dimacs)[0], Zpow2::<40>::zero());
+    }
+
     fn execute_mbf_test<R: RingElement>(secret: R, dimacs: &DIMACS) {
         let parties = mbf_share(vec![secret], &dimacs);
         let subset = get_min_party(&parties, &dimacs);
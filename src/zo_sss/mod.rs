@@ -1,31 +1,93 @@
 pub mod dimacs;
 pub mod mbf;
 
-use crate::{field::Fp, zo_sss::{dimacs::DIMACS, mbf::{mbf_combine, mbf_share}}, RingElement};
+use std::collections::HashSet;
 
-#[derive(Clone)]
+use crate::{error::TfheError, secret::Secret, zo_sss::{dimacs::DIMACS, mbf::{check_sat, find_all_min_sat, mbf_combine, mbf_share}}, RingElement};
+
+/// shares[i][j] is the share with id share_ids[j] of the i-th secret.
+/// Shares are wiped on drop and redacted in `Debug` output.
+#[derive(Debug, Clone)]
 pub struct Party<R: RingElement> {
     pub name: u8,
-    pub shares: Vec<Vec<R>>
+    // Index of every share (column of the share matrix) in the same order as each shares[i]
+    pub share_ids: Vec<u8>,
+    shares: Secret<Vec<Vec<R>>>
+}
+
+impl<R: RingElement> Party<R> {
+    pub fn new(name: u8, share_ids: Vec<u8>, shares: Vec<Vec<R>>) -> Self {
+        Self { name, share_ids, shares: Secret::new(shares) }
+    }
+
+    pub fn shares(&self) -> &[Vec<R>] {
+        self.shares.expose()
+    }
+
+    /// Adds the shares of another sharing (held by the same party) share by share.
+    /// Shares are matched by share id, so the result shares the sum of both secrets.
+    pub fn add_shares(&mut self, other: &Party<R>) {
+        assert_eq!(self.name, other.name, "Can only add shares of the same party");
+        assert_eq!(self.shares().len(), other.shares().len(), "Number of secrets must match");
+
+        for (j, id) in self.share_ids.iter().enumerate() {
+            let k = other.share_ids.iter().position(|o| o == id).expect("Share ids must match");
+            for (w, o) in self.shares.expose_mut().iter_mut().zip(other.shares().iter()) {
+                w[j] += o[k];
+            }
+        }
+    }
 }
 
 pub trait SecretSharingScheme<R:RingElement> {
+    /// Shares every element of `secrets`.
+    /// Implementations take ownership of the secrets and must wipe them (and any intermediate
+    /// values) before returning; callers such as key generation rely on this.
     fn share(&self, secrets: Vec<R>) -> Vec<Party<R>>;
     fn combine(&self, parties: Vec<Party<R>>, is_minimal: bool) -> Vec<R>;
+    fn is_qualified(&self, names: &HashSet<u8>) -> bool;
+    /// All minimal qualified subsets of `names`, fails if there are too many to enumerate
+    fn min_qualified_sets(&self, names: &HashSet<u8>) -> Result<Vec<HashSet<u8>>, TfheError>;
+    /// Total number of distinct shares per secret
+    fn num_shares(&self) -> usize;
+    /// Parties are named 1 to num_parties
+    fn num_parties(&self) -> usize;
 }
 
 pub struct MBF {
     dimacs: DIMACS
 }
 
-impl SecretSharingScheme<Fp> for MBF {
-    fn share(&self, secrets: Vec<Fp>) -> Vec<Party<Fp>> {
+impl MBF {
+    pub fn new(dimacs: DIMACS) -> Self {
+        Self { dimacs }
+    }
+}
+
+impl<R: RingElement> SecretSharingScheme<R> for MBF {
+    fn share(&self, secrets: Vec<R>) -> Vec<Party<R>> {
         mbf_share(secrets, &self.dimacs)
     }
 
-    fn combine(&self, parties: Vec<Party<Fp>>, is_minimal: bool) -> Vec<Fp> {
+    fn combine(&self, parties: Vec<Party<R>>, is_minimal: bool) -> Vec<R> {
         mbf_combine(parties, is_minimal, &self.dimacs)
     }
+
+    fn is_qualified(&self, names: &HashSet<u8>) -> bool {
+        check_sat(names, &self.dimacs)
+    }
+
+    fn min_qualified_sets(&self, names: &HashSet<u8>) -> Result<Vec<HashSet<u8>>, TfheError> {
+        find_all_min_sat(names, &self.dimacs)
+    }
+
+    fn num_shares(&self) -> usize {
+        self.dimacs.num_clauses as usize
+    }
+
+    fn num_parties(&self) -> usize {
+        self.dimacs.num_var as usize
+    }
 }
 
 #[cfg(test)]